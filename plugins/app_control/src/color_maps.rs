@@ -0,0 +1,176 @@
+use config_plugin::{ColorMap, ColorMapValue};
+
+use crate::*;
+
+#[derive(Resource, Default)]
+/// Values of the color maps of the current configuration, computed for every frame when the c3d is loaded.
+/// The markers and the joins that are not in the color map keep their color.
+pub struct ColorMapValues {
+    /// Values of each marker, by label
+    pub markers: Option<ColorMapTrajectories<Vec<f32>>>,
+    /// Values of each join, by the label of its first point and then by the label of the second, so they are found
+    /// without building a key
+    pub joins: Option<ColorMapTrajectories<HashMap<String, Vec<f32>>>>,
+}
+
+/// Value of a color map for every frame, by label. The frames where the value is not defined (a missing marker) are NaN.
+pub struct ColorMapTrajectories<V> {
+    pub color_map: ColorMap,
+    /// Minimum and maximum of the values in the trial, used if the color map has no range
+    pub auto_range: [f64; 2],
+    values: HashMap<String, V>,
+}
+
+impl<V> ColorMapTrajectories<V> {
+    fn new(color_map: ColorMap, values: HashMap<String, V>, trajectories: impl Fn(&V) -> Vec<&Vec<f32>>) -> Self {
+        let (min, max) = values.values().flat_map(trajectories).flatten().filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(*v as f64), max.max(*v as f64)));
+        let auto_range = if min <= max { [min, max] } else { [0.0, 1.0] };
+        ColorMapTrajectories { color_map, auto_range, values }
+    }
+
+    /// Range of the legend: the one of the color map, or the one of the trial
+    pub fn range(&self) -> [f64; 2] {
+        self.color_map.range.unwrap_or(self.auto_range)
+    }
+
+    /// Color of a trajectory in a frame. None if there is no trajectory, or its value is not defined in that frame
+    fn trajectory_color(&self, trajectory: Option<&Vec<f32>>, frame: usize) -> Option<Color> {
+        let value = *trajectory?.get(frame)?;
+        if !value.is_finite() {
+            return None;
+        }
+        let [r, g, b] = self.color_map.color(value as f64, self.auto_range);
+        Some(Color::srgb_u8(r, g, b))
+    }
+}
+
+impl ColorMapTrajectories<Vec<f32>> {
+    /// Whether the marker is in the color map
+    pub fn contains(&self, label: &str) -> bool {
+        self.values.contains_key(label)
+    }
+
+    /// Color of a marker in a frame. None if the marker is not in the color map, or its value is not defined in that frame
+    pub fn color(&self, label: &str, frame: usize) -> Option<Color> {
+        self.trajectory_color(self.values.get(label), frame)
+    }
+}
+
+impl ColorMapTrajectories<HashMap<String, Vec<f32>>> {
+    /// Color of the join between two markers in a frame. None if the join is not in the color map, or its value is not
+    /// defined in that frame
+    pub fn color(&self, point1: &str, point2: &str, frame: usize) -> Option<Color> {
+        self.trajectory_color(self.values.get(point1).and_then(|joins| joins.get(point2)), frame)
+    }
+}
+
+/// Computes the color maps of the current configuration for every frame. `labels` are the labels of the markers, in the
+/// order they are spawned (the ones of the c3d, and then the virtual points), so the index of a label is the one of get_point.
+pub(crate) fn compute_color_maps(
+    current_config: &str,
+    config_file: &ConfigFile,
+    asset: &C3dAsset,
+    labels: &[String],
+) -> ColorMapValues {
+    let Some(config) = config_file.get_config(current_config) else {
+        return ColorMapValues::default();
+    };
+    let num_frames = asset.c3d.points.size().0;
+    let frame_rate = asset.c3d.points.frame_rate;
+    // Position in metres, None if the marker is missing in that frame
+    let position = |frame: usize, i: usize| {
+        let point = asset.get_point_in_metres(frame, i);
+        (point != [0.0; 3]).then(|| Vec3::from(point))
+    };
+    let index = |label: &str| labels.iter().position(|l| l == label);
+
+    let markers = config.get_point_color_map().map(|color_map| {
+        let reference = match &color_map.value {
+            ColorMapValue::Distance(reference) => index(&config_file.resolve_label(reference, labels)),
+            _ => None,
+        };
+        let mut values = HashMap::new();
+        for (i, label) in labels.iter().enumerate() {
+            if !config_file.contains_point_regex(current_config, label) {
+                continue;
+            }
+            let trajectory = (0..num_frames).map(|frame| match &color_map.value {
+                ColorMapValue::Speed => {
+                    let (previous, next) = (frame.saturating_sub(1), (frame + 1).min(num_frames - 1));
+                    match (position(previous, i), position(next, i)) {
+                        (Some(p1), Some(p2)) if next > previous => (p2 - p1).length() * frame_rate / (next - previous) as f32,
+                        _ => f32::NAN,
+                    }
+                }
+                ColorMapValue::Distance(_) => match (position(frame, i), reference.and_then(|r| position(frame, r))) {
+                    (Some(p), Some(r)) => (p - r).length(),
+                    _ => f32::NAN,
+                },
+                _ => f32::NAN,
+            }).collect::<Vec<_>>();
+            values.insert(label.clone(), trajectory);
+        }
+        ColorMapTrajectories::new(color_map.clone(), values, |trajectory| vec![trajectory])
+    });
+
+    let joins = config.get_join_color_map().map(|color_map| {
+        let mut values: HashMap<String, HashMap<String, Vec<f32>>> = HashMap::new();
+        for join in joins_in_config(current_config, config_file, labels) {
+            let (Some(i1), Some(i2)) = (index(&join.0), index(&join.1)) else {
+                continue;
+            };
+            let lengths = (0..num_frames).map(|frame| match (position(frame, i1), position(frame, i2)) {
+                (Some(p1), Some(p2)) => (p1 - p2).length(),
+                _ => f32::NAN,
+            }).collect::<Vec<_>>();
+            let trajectory = match color_map.value {
+                ColorMapValue::LengthChange => {
+                    let first = lengths.iter().copied().find(|l| l.is_finite()).unwrap_or(f32::NAN);
+                    lengths.iter().map(|l| l - first).collect()
+                }
+                _ => lengths,
+            };
+            values.entry(join.0.clone()).or_default().insert(join.1.clone(), trajectory);
+        }
+        ColorMapTrajectories::new(color_map.clone(), values, |joins| joins.values().collect())
+    });
+
+    ColorMapValues { markers, joins }
+}
+
+/// Colors the markers and the joins of the color maps with their value in the frame that is represented.
+/// Materials are only updated when the frame or the color maps change (they are replaced when the configuration is reloaded).
+pub(crate) fn represent_color_maps(
+    state: Res<AppState>,
+    color_maps: Res<ColorMapValues>,
+    mut last_frame: Local<Option<usize>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query_markers: Query<(&Marker, &MeshMaterial3d<StandardMaterial>)>,
+    query_joins: Query<(&Join, &MeshMaterial3d<StandardMaterial>)>,
+) {
+    if state.num_frames == 0 || (color_maps.markers.is_none() && color_maps.joins.is_none()) {
+        return;
+    }
+    // represent_points moves to the next frame after representing one
+    let frame = (state.frame + state.num_frames - 1) % state.num_frames;
+    if *last_frame == Some(frame) && !color_maps.is_changed() {
+        return;
+    }
+    *last_frame = Some(frame);
+
+    if let Some(trajectories) = &color_maps.markers {
+        for (marker, material) in query_markers.iter() {
+            if let (Some(color), Some(material)) = (trajectories.color(&marker.0, frame), materials.get_mut(&material.0)) {
+                material.base_color = color;
+            }
+        }
+    }
+    if let Some(trajectories) = &color_maps.joins {
+        for (join, material) in query_joins.iter() {
+            if let (Some(color), Some(material)) = (trajectories.color(&join.0, &join.1, frame), materials.get_mut(&material.0)) {
+                material.base_color = color;
+            }
+        }
+    }
+}
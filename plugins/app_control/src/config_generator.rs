@@ -0,0 +1,36 @@
+use bevy::asset::{io::AssetReaderError, AssetLoadError, LoadState};
+
+use crate::*;
+
+/// Builds a configuration from the labels of the c3d (see ConfigFile::from_labels) when there is no configuration file,
+/// or the file does not exist. The generated configuration is added as the configuration asset, so it is selected by
+/// select_config_for_c3d and can be saved from the GUI (see GeneratedConfig). It is generated again when another c3d is loaded, until a
+/// configuration file is loaded.
+pub(crate) fn generate_config_for_c3d(
+    mut generated_config: ResMut<GeneratedConfig>,
+    mut config_state: ResMut<ConfigState>,
+    mut config_assets: ResMut<Assets<ConfigC3dAsset>>,
+    asset_server: Res<AssetServer>,
+    c3d_state: Res<C3dState>,
+    c3d_assets: Res<Assets<C3dAsset>>,
+) {
+    let Some(c3d_asset) = c3d_assets.get(&c3d_state.handle) else {
+        return;
+    };
+    let c3d_id = c3d_state.handle.id();
+    let without_config = match asset_server.get_load_state(&config_state.handle) {
+        None => config_assets.get(&config_state.handle).is_none(),
+        Some(LoadState::Failed(error)) => matches!(*error, AssetLoadError::AssetReaderError(AssetReaderError::NotFound(_))),
+        Some(_) => false,
+    };
+    let outdated = generated_config.generated.as_ref().is_some_and(|(id, handle)| *handle == config_state.handle && *id != c3d_id);
+    if !without_config && !outdated {
+        return;
+    }
+
+    let config = ConfigFile::from_labels(&get_all_labels(&c3d_asset.c3d));
+    let handle = config_assets.add(ConfigC3dAsset { config_str: config.to_toml_string(), config });
+    println!("No configuration file, using a configuration generated from the labels of the c3d");
+    config_state.handle = handle.clone();
+    generated_config.generated = Some((c3d_id, handle));
+}
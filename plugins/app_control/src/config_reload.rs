@@ -0,0 +1,171 @@
+use bevy::ecs::system::SystemParam;
+
+use crate::*;
+
+#[derive(Resource, Default)]
+/// Configuration file the scene was built with. When the configuration file is reloaded, it is compared with the new one
+/// to update only the entities that change.
+pub(crate) struct AppliedConfig(pub(crate) Option<ConfigFile>);
+
+/// Configuration file, with its load events and the configuration it replaces
+#[derive(SystemParam)]
+pub(crate) struct ConfigReload<'w, 's> {
+    events: EventReader<'w, 's, AssetEvent<ConfigC3dAsset>>,
+    state: Res<'w, ConfigState>,
+    assets: Res<'w, Assets<ConfigC3dAsset>>,
+    applied: ResMut<'w, AppliedConfig>,
+    validation: ResMut<'w, ConfigValidation>,
+}
+
+impl ConfigReload<'_, '_> {
+    /// Whether the configuration file was loaded or modified since the last call. All the events are read, so they are not
+    /// seen again in the next frame.
+    fn reloaded(&mut self) -> bool {
+        let id = self.state.handle.id();
+        self.events.read().filter(|event| match event {
+            AssetEvent::LoadedWithDependencies { id: event_id } | AssetEvent::Modified { id: event_id } => *event_id == id,
+            _ => false,
+        }).count() > 0
+    }
+}
+
+/// Entities of the scene that depend on the configuration, and what is needed to spawn them again
+#[derive(SystemParam)]
+pub(crate) struct ConfigScene<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    query_markers: Query<'w, 's, (&'static mut Marker, &'static mut Visibility, &'static mut Mesh3d, &'static MeshMaterial3d<StandardMaterial>)>,
+    query_joins: Query<'w, 's, (Entity, &'static Join)>,
+    query_vectors: Query<'w, 's, (Entity, &'static Vector)>,
+    color_maps: ResMut<'w, ColorMapValues>,
+}
+
+/// Applies the configuration file when it is modified on disk (or reloaded with F5), or when a new one is loaded, without
+/// rebuilding the scene. Only the markers whose style or visibility change are updated, only the joins that change are
+/// respawned, and the vectors are respawned if they change. The frame, the traces and the graphs are kept.
+///
+/// Virtual points, color maps, surfaces, the unit scale and the axes of the lab are used when the c3d is loaded, so if the
+/// configuration has any of them and it changes, the markers are rebuilt (as when changing the config). If the color maps
+/// do not change, their values are computed again for the new markers and joins, and represent_color_maps repaints them.
+pub(crate) fn apply_config_changes(
+    mut config_reload: ConfigReload,
+    mut scene: ConfigScene,
+    mut ev_loaded: EventWriter<C3dLoadedEvent>,
+    state: Res<AppState>,
+    c3d_state: Res<C3dState>,
+    c3d_assets: Res<Assets<C3dAsset>>,
+) {
+    if !config_reload.reloaded() {
+        return;
+    }
+    let Some(config) = config_reload.assets.get(&config_reload.state.handle).map(|asset| &asset.config) else {
+        return;
+    };
+    // Without c3d there is nothing to update, load_c3d will use the new configuration
+    let Some(c3d_asset) = c3d_assets.get(&c3d_state.handle) else {
+        return;
+    };
+    if config_reload.applied.0.as_ref() == Some(config) {
+        return;
+    }
+    let old_config = config_reload.applied.0.as_ref();
+    let current_config = state.current_config.as_deref().unwrap_or("");
+    let labels = get_all_labels(&c3d_asset.c3d);
+
+    // Vectors are few, they are respawned if they change
+    let vectors = |config: Option<&ConfigFile>| config.map(|config| (
+        config.get_config(current_config).and_then(|c| c.get_vectors()).cloned(),
+        config.get_aliases().cloned(),
+        config.get_defaults().get_vector_colors(),
+        config.get_defaults().get_vector_color(),
+    ));
+    if vectors(old_config) != vectors(Some(config)) {
+        despawn_all_vectors(&mut scene.commands, &scene.query_vectors);
+        spawn_vectors_in_config(current_config, config, &labels, &mut scene.commands, &mut scene.meshes, &mut scene.materials);
+    }
+
+    let old_virtual_points = old_config.map(|config| config.get_virtual_points().clone()).unwrap_or_default();
+    let color_maps = |config: Option<&ConfigFile>| config.and_then(|config| config.get_config(current_config)).map(|config| (
+        config.get_point_color_map().cloned(),
+        config.get_join_color_map().cloned(),
+    ));
+    // Surfaces are compared with their points resolved, as the aliases can change them
+    let surfaces = |config_file: Option<&ConfigFile>| config_file.and_then(|config_file| {
+        let surfaces = config_file.get_config(current_config)?.get_surfaces()?;
+        Some(surfaces.iter().map(|surface| (
+            surface.points.iter().map(|point| config_file.resolve_label(point, &labels)).collect::<Vec<_>>(),
+            surface.color,
+        )).collect::<Vec<_>>())
+    });
+    let unit_scale = |config: Option<&ConfigFile>| config_defaults(config).get_unit_scale();
+    let lab_axes = |config: Option<&ConfigFile>| config_defaults(config).get_lab_axes();
+    if old_virtual_points != *config.get_virtual_points() || color_maps(old_config) != color_maps(Some(config))
+        || surfaces(old_config) != surfaces(Some(config)) || unit_scale(old_config) != unit_scale(Some(config))
+        || lab_axes(old_config) != lab_axes(Some(config)) {
+        despawn_all_joins(&mut scene.commands, &scene.query_joins);
+        ev_loaded.send(C3dLoadedEvent);
+        println!("Virtual points, color maps, surfaces, unit scale or axes changed, rebuilding the markers");
+        return;
+    }
+
+    // Replacing the values makes represent_color_maps repaint the frame
+    if color_maps(Some(config)).is_some_and(|maps| maps != (None, None)) {
+        let marker_labels = labels.iter().cloned().chain(c3d_asset.virtual_points.iter().map(|(label, _)| label.clone())).collect::<Vec<_>>();
+        *scene.color_maps = compute_color_maps(current_config, config, c3d_asset, &marker_labels);
+    }
+
+    let mut updated_markers = 0;
+    for (mut marker, mut visibility, mut mesh, material) in scene.query_markers.iter_mut() {
+        let label = marker.0.clone();
+        let mut updated = false;
+
+        // The markers of the color map are colored by represent_color_maps
+        let color_mapped = scene.color_maps.markers.as_ref().is_some_and(|trajectories| trajectories.contains(&label));
+        let color = marker_color(&label, current_config, Some(config));
+        if !color_mapped && color != marker_color(&label, current_config, old_config) {
+            if let Some(material) = scene.materials.get_mut(&material.0) {
+                material.base_color = color;
+            }
+            updated = true;
+        }
+        let radius = marker_radius(&label, current_config, Some(config));
+        if radius != marker_radius(&label, current_config, old_config) {
+            mesh.0 = scene.meshes.add(Sphere::new(radius).mesh());
+            updated = true;
+        }
+        let marker_visibility = marker_visibility(&label, current_config, Some(config));
+        if marker_visibility != marker.1 {
+            // Markers hidden because they are missing in this frame stay hidden until represent_points shows them
+            if *visibility == marker.1 {
+                *visibility = marker_visibility;
+            }
+            marker.1 = marker_visibility;
+            updated = true;
+        }
+        if updated {
+            updated_markers += 1;
+        }
+    }
+
+    // Joins that are in both configurations are kept. The rest are despawned, and the new ones spawned
+    let mut new_joins = joins_in_config(current_config, config, &labels);
+    let mut despawned_joins = 0;
+    for (entity, join) in scene.query_joins.iter() {
+        match new_joins.iter().position(|new_join| new_join == join) {
+            Some(i) => { new_joins.remove(i); }
+            None => {
+                scene.commands.entity(entity).despawn_recursive();
+                despawned_joins += 1;
+            }
+        }
+    }
+    let spawned_joins = new_joins.len();
+    for join in new_joins {
+        spawn_join(join, &mut scene.commands, &mut scene.meshes, &mut scene.materials);
+    }
+
+    config_reload.validation.report = Some(config.validate(&labels));
+    config_reload.applied.0 = Some(config.clone());
+    println!("Configuration applied: {} markers updated, {} joins removed, {} joins added", updated_markers, despawned_joins, spawned_joins);
+}
@@ -0,0 +1,53 @@
+use crate::*;
+
+#[derive(Resource, Default, Debug)]
+/// ConfigSelection contains the configuration chosen automatically for the labels of the last c3d file loaded.
+pub struct ConfigSelection {
+    /// Name of the configuration and its score (the fraction of its visible points found in the c3d, see ConfigFile::match_score).
+    /// None if no configuration fits the c3d.
+    pub selected: Option<(String, f64)>,
+}
+
+/// Chooses the configuration that fits best the labels of the c3d (see ConfigFile::best_config_for_labels) when a new c3d or
+/// a new configuration file is loaded. The configuration is changed as if the user selected it, so the scene is rebuilt.
+/// If no configuration fits, the current one is kept, or the first one is chosen if the current one is not in the file.
+///
+/// Reloading the same files keeps the configuration, so the user can change it afterwards.
+pub(crate) fn select_config_for_c3d(
+    mut last_selection: Local<Option<(AssetId<C3dAsset>, AssetId<ConfigC3dAsset>)>>,
+    mut state: ResMut<AppState>,
+    mut selection: ResMut<ConfigSelection>,
+    c3d_state: Res<C3dState>,
+    c3d_assets: Res<Assets<C3dAsset>>,
+    config_state: Res<ConfigState>,
+    config_assets: Res<Assets<ConfigC3dAsset>>,
+) {
+    let key = (c3d_state.handle.id(), config_state.handle.id());
+    if *last_selection == Some(key) {
+        return;
+    }
+    let (Some(c3d_asset), Some(config_asset)) = (c3d_assets.get(&c3d_state.handle), config_assets.get(&config_state.handle)) else {
+        return;
+    };
+    *last_selection = Some(key);
+
+    let labels = get_all_labels(&c3d_asset.c3d);
+    let config_file = &config_asset.config;
+    selection.selected = config_file.best_config_for_labels(&labels);
+
+    let config_name = match &selection.selected {
+        Some((config_name, score)) => {
+            println!("Configuration {:?} selected for the c3d ({:.0}% of its points found)", config_name, score * 100.0);
+            Some(config_name.clone())
+        }
+        None => {
+            println!("No configuration fits the labels of the c3d");
+            let current_exists = state.current_config.as_ref().is_some_and(|current| config_file.get_config(current).is_some());
+            (!current_exists).then(|| config_file.get_all_config_names().first().cloned()).flatten()
+        }
+    };
+    if let Some(config_name) = config_name.filter(|name| state.current_config.as_ref() != Some(name)) {
+        state.current_config = Some(config_name);
+        state.change_config = true;
+    }
+}
@@ -1,6 +1,6 @@
 use std::ffi::OsStr;
 
-use config_plugin::ConfigState;
+use config_plugin::{ConfigState, LabelCatalogState};
 
 use crate::*;
 
@@ -28,10 +28,14 @@ pub fn file_drop(
                         state.frame = 0;
                         ev_loaded.send(C3dLoadedEvent);
                     }
-                    if extension == OsStr::new("toml") {
+                    if extension == OsStr::new("toml") || extension == OsStr::new("yaml") || extension == OsStr::new("yml") || extension == OsStr::new("mkr") {
                         state.config_path = path_buf.to_str().unwrap().to_string();
                         state.reload_config = true;
                     }
+                    if extension == OsStr::new("csv") || extension == OsStr::new("xlsx") {
+                        state.labels_path = path_buf.to_str().unwrap().to_string();
+                        state.reload_labels = true;
+                    }
                 },
                 None => {},
             }
@@ -60,10 +64,29 @@ pub fn update_configc3d_path(
     asset_server: Res<AssetServer>,
 ) {
     if state.reload_config {
-        conf_state.handle = asset_server.load(state.config_path.clone());
+        // The new configuration is applied by apply_config_changes when it is loaded, without rebuilding the scene.
+        // If it is the same file, it is already loaded, so it must be reloaded to read the changes.
+        let handle = asset_server.load(state.config_path.clone());
+        if handle == conf_state.handle {
+            asset_server.reload(state.config_path.clone());
+        }
+        conf_state.handle = handle;
 
         state.reload_config = false;
-        state.reload_c3d = true;
         println!("Config file reloaded");
     }
+}
+
+pub fn update_labels_path(
+    mut state: ResMut<AppState>,
+    mut labels_state: ResMut<LabelCatalogState>,
+    asset_server: Res<AssetServer>,
+) {
+    if state.reload_labels {
+        labels_state.path = state.labels_path.clone();
+        labels_state.handle = asset_server.load(state.labels_path.clone());
+
+        state.reload_labels = false;
+        println!("Label table reloaded");
+    }
 }
\ No newline at end of file
@@ -1,10 +1,19 @@
-use config_plugin::JoinShape;
+use config_plugin::{ConfigColor, JoinShape};
 
 use crate::*;
 
-#[derive(Component)]
-/// This represents the joins between the points in the C3D file. It contains the labels of the points that are joined and the shape of the join.
-pub struct Join(pub(crate) String, pub(crate) String, JoinShape);
+#[derive(Component, Clone, PartialEq)]
+/// This represents the joins between the points in the C3D file. It contains the labels of the points that are joined, the shape of the join and its style.
+/// A spline joins all the points of the join of the configuration in a single entity: the labels are the ends, and the last field has all the points.
+/// The other shapes have one entity per segment, and the last field is empty.
+pub struct Join(pub(crate) String, pub(crate) String, JoinShape, JoinStyle, pub(crate) Vec<String>);
+
+#[derive(Clone, PartialEq)]
+/// Color and line thickness of a join, as resolved from the configuration when the join is spawned.
+pub(crate) struct JoinStyle {
+    color: ConfigColor,
+    line_thickness: f64,
+}
 
 #[derive(Event)]
 pub enum JoinEvent {
@@ -12,44 +21,84 @@ pub enum JoinEvent {
     DespawnJoinEvent(String, String),
 }
 
+/// Spawns the joins of the configuration. The points of the joins are resolved to the labels of the C3D (see ConfigFile::resolve_label).
 pub(crate) fn spawn_joins_in_config(
     current_config: &str,
     config_file: &ConfigFile,
+    labels: &[String],
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
 ){
-    if config_file.get_config(current_config).is_some(){
-        if let Some(joins) = config_file.get_config(current_config).unwrap().get_joins(){
-            for (join, shape) in joins {
-                for i in 0..join.len() - 1 {
-                    let line_color = config_file.get_join_color(&join[i], &join[i+1], &current_config).unwrap_or(vec![0, 255, 0]);
-                    let join_material = standard_material_with_color(materials, line_color);
-                    let join_mesh = match shape {
-                        JoinShape::Line => line_config(current_config, config_file, meshes, &join[i], &join[i+1]),
-                        JoinShape::Cylinder(radius) => cylinder_config(meshes, *radius),
-                        JoinShape::SemiCone(radius1, radius2) => semicone_config(meshes, *radius1, *radius2),
-                        JoinShape::RectangularPrism(width, height, _) => rectangular_prism_config(meshes, *width, *height),
-                    };
-                    commands.spawn((
-                        Mesh3d(join_mesh),
-                        MeshMaterial3d(join_material),
-                        Transform::from_translation(Vec3::new(0.0, 0.5, 0.0)),
-                        Join(join[i].clone(), join[i+1].clone(), shape.clone())));
-                }
+    for join in joins_in_config(current_config, config_file, labels) {
+        spawn_join(join, commands, meshes, materials);
+    }
+}
+
+/// Joins of the configuration, one per segment, without spawning them. Used to spawn them and to compare two versions of a configuration.
+pub(crate) fn joins_in_config(
+    current_config: &str,
+    config_file: &ConfigFile,
+    labels: &[String],
+) -> Vec<Join> {
+    let mut segments = Vec::new();
+    let defaults = config_file.get_defaults();
+    if let Some(joins) = config_file.get_config(current_config).and_then(|config| config.get_joins()) {
+        for (join, shape) in joins {
+            if let JoinShape::Spline(_) = shape {
+                let style = JoinStyle {
+                    color: config_file.get_join_color(&join[0], &join[1], current_config).unwrap_or_else(|| defaults.get_join_color()),
+                    line_thickness: config_file.get_line_thickness(&join[0], &join[1], current_config).unwrap_or(defaults.get_line_thickness()),
+                };
+                let points = join.iter().map(|point| config_file.resolve_label(point, labels)).collect::<Vec<_>>();
+                segments.push(Join(points[0].clone(), points[points.len() - 1].clone(), shape.clone(), style, points));
+                continue;
+            }
+            for i in 0..join.len() - 1 {
+                let style = JoinStyle {
+                    color: config_file.get_join_color(&join[i], &join[i+1], current_config).unwrap_or_else(|| defaults.get_join_color()),
+                    line_thickness: config_file.get_line_thickness(&join[i], &join[i+1], current_config).unwrap_or(defaults.get_line_thickness()),
+                };
+                let shape = match shape {
+                    JoinShape::RectangularPrism(width, height, Some(orientation)) => 
+                        JoinShape::RectangularPrism(*width, *height, Some(orientation.clone().map(|vector| config_file.resolve_label(&vector, labels)))),
+                    _ => shape.clone(),
+                };
+                segments.push(Join(config_file.resolve_label(&join[i], labels), config_file.resolve_label(&join[i+1], labels), shape, style, Vec::new()));
             }
         }
     }
+    segments
+}
+
+pub(crate) fn spawn_join(
+    join: Join,
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
+    let join_material = standard_material_with_color(materials, join.3.color);
+    let join_mesh = match join.2 {
+        JoinShape::Line => line_config(meshes, join.3.line_thickness),
+        JoinShape::Cylinder(radius) => cylinder_config(meshes, radius),
+        JoinShape::SemiCone(radius1, radius2) => semicone_config(meshes, radius1, radius2),
+        JoinShape::RectangularPrism(width, height, _) => rectangular_prism_config(meshes, width, height),
+        JoinShape::Ellipsoid(rx, rz) => ellipsoid_config(meshes, rx, rz),
+        // Capsules and splines are built by represent_joins, with the positions of the markers
+        JoinShape::Capsule(_) | JoinShape::Spline(_) => meshes.add(Mesh::from(Sphere::new(0.0))),
+    };
+    commands.spawn((
+        Mesh3d(join_mesh),
+        MeshMaterial3d(join_material),
+        Transform::from_translation(Vec3::new(0.0, 0.5, 0.0)),
+        join));
 }
 
 fn line_config(
-    current_config: &str, 
-    config_file: &ConfigFile, 
     meshes: &mut ResMut<'_, Assets<Mesh>>, 
-    p1: &str,
-    p2: &str,
+    line_thickness: f64,
 ) -> Handle<Mesh> {
-    let line_thickness = config_file.get_line_thickness(p1, p2, &current_config).unwrap_or(0.01) as f32;
+    let line_thickness = line_thickness as f32;
     let join_mesh =  meshes.add(
     Cylinder::new(
         if line_thickness > 0.01 { line_thickness * 0.01 } else { 0.01 },
@@ -89,26 +138,87 @@ fn rectangular_prism_config(
     )
 }
 
-fn standard_material_with_color(materials: &mut ResMut<'_, Assets<StandardMaterial>>, line_color: Vec<u8>) -> Handle<StandardMaterial> {
+fn ellipsoid_config(
+    meshes: &mut ResMut<'_, Assets<Mesh>>, 
+    rx: f64,
+    rz: f64,
+) -> Handle<Mesh> {
+    // Scaled as the other shapes: 0.5 across the segment and the length of the segment along it
+    meshes.add(Sphere::new(1.0).mesh().build().scaled_by(Vec3::new((rx * 0.01) as f32, 0.5, (rz * 0.01) as f32)))
+}
+
+/// Capsule from `start` to `end`, with rounded ends around them. Same radius as a cylinder of that radius.
+fn capsule_mesh(radius: f64, length: f32) -> Mesh {
+    Capsule3d::new((radius * 0.005) as f32, length).mesh().build()
+}
+
+/// Tube through the points, following a Catmull-Rom curve. Same radius as a cylinder of that radius.
+fn spline_mesh(points: &[Vec3], radius: f64) -> Mesh {
+    const SAMPLES_PER_SEGMENT: usize = 8;
+    const SIDES: usize = 8;
+    let radius = (radius * 0.005) as f32;
+
+    let mut path = Vec::new();
+    for i in 0..points.len() - 1 {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(points.len() - 1)];
+        for sample in 0..SAMPLES_PER_SEGMENT {
+            let t = sample as f32 / SAMPLES_PER_SEGMENT as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            path.push(0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3));
+        }
+    }
+    path.push(points[points.len() - 1]);
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+    // The ring of each sample is perpendicular to the curve. The normal is carried along the curve to avoid twists
+    let mut normal = (path[1] - path[0]).normalize_or_zero().any_orthonormal_vector();
+    for (i, center) in path.iter().enumerate() {
+        let tangent = (path[(i + 1).min(path.len() - 1)] - path[i.saturating_sub(1)]).normalize_or_zero();
+        normal = (normal - tangent * normal.dot(tangent)).normalize_or(tangent.any_orthonormal_vector());
+        let binormal = tangent.cross(normal);
+        for side in 0..SIDES {
+            let angle = side as f32 / SIDES as f32 * std::f32::consts::TAU;
+            let direction = normal * angle.cos() + binormal * angle.sin();
+            positions.push((*center + direction * radius).to_array());
+            normals.push(direction.to_array());
+        }
+        if i > 0 {
+            let (previous, current) = ((i - 1) * SIDES, i * SIDES);
+            for side in 0..SIDES {
+                let next = (side + 1) % SIDES;
+                indices.extend([previous + side, current + side, current + next, previous + side, current + next, previous + next].map(|i| i as u32));
+            }
+        }
+    }
+
+    Mesh::new(bevy::render::mesh::PrimitiveTopology::TriangleList, bevy::render::render_asset::RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_indices(bevy::render::mesh::Indices::U32(indices))
+}
+
+fn standard_material_with_color(materials: &mut ResMut<'_, Assets<StandardMaterial>>, line_color: ConfigColor) -> Handle<StandardMaterial> {
     let join_material = materials.add(StandardMaterial {
-        base_color: // TODO: Add transparency (not working) 
-            if line_color.len() == 3 {
-                Color::srgb_u8(line_color[0], line_color[1],line_color[2])
-            } else if line_color.len() == 4 {
-                Color::srgba_u8(line_color[0], line_color[1], line_color[2], line_color[3])
-            } else{
-                Color::srgb_u8(0, 127, 0)
-            },
+        base_color: Color::from(line_color), // TODO: Add transparency (not working) 
         alpha_mode: AlphaMode::Blend,
         ..default()
     });
     join_material
 }
 
+/// Places every join between its markers. Capsules and splines have no scale: their mesh is rebuilt with the positions of
+/// the markers (capsules only when their length changes, so their ends stay round).
 pub fn represent_joins(
     mut join_event: EventWriter<JoinEvent>,
     markers_query: Query<(&Marker, &Transform)>,
-    mut joins_query: Query<(&mut Transform, &Join), Without<Marker>>,
+    mut joins_query: Query<(Entity, &mut Transform, &Join, &Mesh3d), Without<Marker>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut capsule_lengths: Local<HashMap<Entity, f32>>,
     c3d_state: Res<C3dState>,
     c3d_assets: Res<Assets<C3dAsset>>,
 ) {
@@ -116,9 +226,35 @@ pub fn represent_joins(
 
     match asset {
         Some(_asset) => {
-            for (mut transform, join) in joins_query.iter_mut() {
+            capsule_lengths.retain(|entity, _| joins_query.contains(*entity));
+            for (entity, mut transform, join, mesh) in joins_query.iter_mut() {
                 let marker1 = get_marker_position_on_frame(&join.0, &markers_query);
                 let marker2 = get_marker_position_on_frame(&join.1, &markers_query);
+                if let (JoinShape::Spline(radius), Some(_), Some(_)) = (&join.2, marker1, marker2) {
+                    // Markers missing in this frame are at the origin, the curve goes through the rest
+                    let points = join.4.iter()
+                        .filter_map(|label| get_marker_position_on_frame(label, &markers_query))
+                        .filter(|point| *point != Vec3::ZERO)
+                        .collect::<Vec<_>>();
+                    if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                        *mesh = if points.len() > 1 { spline_mesh(&points, *radius) } else { Sphere::new(0.0).mesh().build() };
+                    }
+                    *transform = Transform::IDENTITY;
+                    continue;
+                }
+                if let (JoinShape::Capsule(radius), Some(marker1), Some(marker2)) = (&join.2, marker1, marker2) {
+                    let length = (marker1 - marker2).length();
+                    if capsule_lengths.get(&entity).is_none_or(|previous| (previous - length).abs() > 0.001) {
+                        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                            *mesh = capsule_mesh(*radius, length);
+                        }
+                        capsule_lengths.insert(entity, length);
+                    }
+                    transform.translation = (marker1 + marker2) / 2.0;
+                    transform.rotation = Quat::from_rotation_arc(Vec3::Y, (marker1 - marker2).normalize_or(Vec3::Y));
+                    transform.scale = Vec3::ONE;
+                    continue;
+                }
                 let orientation_option = match &join.2 {
                     JoinShape::RectangularPrism(_, _, option) => option.as_ref(),
                     _ => None
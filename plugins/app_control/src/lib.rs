@@ -1,8 +1,13 @@
+pub mod color_maps;
+mod config_generator;
+mod config_reload;
+mod config_selection;
 mod file_drop;
 mod mouse_keyboard;
 pub mod vectors;
 pub mod markers;
 pub mod joins;
+pub mod surfaces;
 pub mod traces;
 
 use std::{collections::HashMap, vec};
@@ -10,11 +15,17 @@ use std::{collections::HashMap, vec};
 use bevy::{asset::AssetMetaCheck, prelude::*}; 
 use bevy_c3d_mod::*;
 use bevy_web_file_drop::WebFileDropPlugin;
-use config_plugin::{parse_config, C3dConfigPlugin, ConfigC3dAsset, ConfigFile, ConfigState};
+use config_plugin::{C3dConfigPlugin, ConfigC3dAsset, ConfigFile, ConfigState, Defaults, LabAxes, ValidationReport};
+use color_maps::*;
+use config_generator::*;
+use config_reload::*;
+pub use config_selection::ConfigSelection;
+use config_selection::*;
 use mouse_keyboard::*;
 use vectors::*;
 use markers::*;
 use joins::*;
+use surfaces::*;
 use traces::*;
 
 pub struct ControlPlugin;
@@ -25,6 +36,7 @@ impl Plugin for ControlPlugin {
             .add_plugins((WebFileDropPlugin, DefaultPlugins.set(
                 AssetPlugin {
                             meta_check: AssetMetaCheck::Never,
+                            watch_for_changes_override: Some(true),     // Reload the configuration when it changes on disk
                             ..default()
                         }
                 )))
@@ -34,18 +46,19 @@ impl Plugin for ControlPlugin {
             .add_systems(Startup, init_resources)
             .add_systems(First, file_drop::update_c3d_path.run_if(|state: Res<AppState>| -> bool { state.reload_c3d } ))
             .add_systems(First, file_drop::update_configc3d_path.run_if(|state: Res<AppState>| -> bool { state.reload_config } ))
+            .add_systems(First, file_drop::update_labels_path.run_if(|state: Res<AppState>| -> bool { state.reload_labels } ))
             .add_systems(Update, (file_drop::file_drop, mouse_keyboard::keyboard_controls))
-            .add_systems(Update, load_c3d)
+            .add_systems(Update, (generate_config_for_c3d, select_config_for_c3d, load_c3d).chain())
             .add_systems(Update, (represent_points)
                 .run_if(|state: Res<AppState>| -> bool { (state.c3d_file_loaded && state.play) || state.render_frame })
                 .run_if(|state: Res<AppState>| -> bool { state.fixed_frame_rate.is_none() || !state.render_at_fixed_frame_rate }))
             .add_systems(FixedUpdate, (represent_points)
                 .run_if(|state: Res<AppState>| -> bool { (state.c3d_file_loaded && state.play) || state.render_frame })
                 .run_if(|state: Res<AppState>| -> bool { state.fixed_frame_rate.is_some() && state.render_at_fixed_frame_rate }))
-            .add_systems(Update, (represent_joins, represent_vectors))
+            .add_systems(Update, (represent_joins, represent_vectors, represent_color_maps.after(apply_config_changes), represent_surfaces))
             .add_systems(Update, (joins_event_orchestrator, traces_event_orchestrator, vector_event_orchestrator, despawn_all_markers_event))
-            .add_systems(Update, (change_frame_rate, change_config))
-            .add_systems(Update, update_orbit_camera)
+            .add_systems(Update, (change_frame_rate, change_config, apply_config_changes))
+            .add_systems(Update, (update_orbit_camera, update_reference_axes))
             .add_event::<MarkerEvent>()
             .add_event::<JoinEvent>()
             .add_event::<TraceEvent>()
@@ -54,6 +67,11 @@ impl Plugin for ControlPlugin {
             .init_resource::<AppState>()
             .init_resource::<GuiSidesEnabled>()
             .init_resource::<VectorsVisibility>()
+            .init_resource::<ConfigValidation>()
+            .init_resource::<GeneratedConfig>()
+            .init_resource::<AppliedConfig>()
+            .init_resource::<ColorMapValues>()
+            .init_resource::<ConfigSelection>()
             .insert_resource(Time::<Fixed>::from_hz(250.));          // default frame rate, can be changed by the user
         println!("Control Plugin loaded");
     }
@@ -76,6 +94,10 @@ pub struct AppState {
     pub reload_c3d: bool,
     /// Reload the configuration file. Used to reload the configuration file when the path changes.
     pub reload_config: bool,
+    /// Path to the label table (CSV or XLSX) with the descriptions of the labels.
+    pub labels_path: String,
+    /// Reload the label table. Used to reload the label table when the path changes.
+    pub reload_labels: bool,
     /// Change the configuration. Used to change the configuration of the c3d file.
     pub change_config: bool,
     /// File loaded. Used to know if the c3d file is loaded.
@@ -88,6 +110,11 @@ pub struct AppState {
     pub render_frame: bool,
     /// Frame rate of the c3d. You should not modify this value. To adjust the representation speed use render_at_fixed_frame_rate.
     pub frame_rate: Option<f32>,
+    /// Units of the points in the c3d file. You should not modify this value: the points are converted to millimetres when loading.
+    pub units: Option<String>,
+    /// Coordinate system of the lab of the c3d file, chosen in the interface. None uses the one of the configuration ([defaults]) or the c3d loader.
+    /// Set change_config to apply it.
+    pub lab_axes: Option<LabAxes>,
     /// Frame rate of the animation. Fixed is to match the c3d file frame rate, or any other frame rate. May loose information if the frame rate is higher than your hardware maximun.
     pub fixed_frame_rate: Option<f64>,
     /// Render at fixed frame rate. If true, the representation will be at the fixed frame rate. If false, the representation will be at the Update schedule decides (typically 60 Hz).
@@ -96,6 +123,20 @@ pub struct AppState {
     pub traces: TraceInfo,
 }
 
+#[derive(Resource, Default, Debug)]
+/// ConfigValidation contains the result of checking the configuration file against the labels of the c3d file. It is updated every time the c3d is loaded.
+pub struct ConfigValidation {
+    /// None if there is no configuration or c3d loaded.
+    pub report: Option<ValidationReport>,
+}
+
+#[derive(Resource, Default, Debug)]
+/// GeneratedConfig contains the configuration generated from the labels of the c3d, when there is no configuration file.
+pub struct GeneratedConfig {
+    /// Handle of the generated configuration and the c3d it was generated from. None until a configuration is generated.
+    pub generated: Option<(AssetId<C3dAsset>, Handle<ConfigC3dAsset>)>,
+}
+
 #[derive(Event)]
 /// MilestoneEvent contains the events related to the milestones.
 pub enum MilestoneEvent {
@@ -125,9 +166,13 @@ impl AppState {
             c3d_file_loaded: false,
             change_config: false,
             reload_config: false,
+            labels_path: "".to_string(),
+            reload_labels: false,
             play: false,
             render_frame: false,
             frame_rate: None,
+            units: None,
+            lab_axes: None,
             fixed_frame_rate: None,
             render_at_fixed_frame_rate: false,
             // config: None,
@@ -155,8 +200,10 @@ fn init_resources(
 ) {
     state.frame = 0;
     state.c3d_path =  "".to_string();
-    state.current_config = Some("config1".to_string());
+    state.current_config = None;                    // Chosen by select_config_for_c3d when the c3d and the configuration are loaded
     state.config_path = "config_file.toml".to_string();
+    state.labels_path = "EtiquetasVariables3.xlsx".to_string();
+    state.reload_labels = true;
     state.reload_c3d = true;
     state.c3d_file_loaded = true;
     state.play = true;
@@ -169,6 +216,13 @@ fn init_resources(
     println!("Control PluginSetup done");
 }
 
+/// Up axis of the scene. The points of the c3d are converted to it when loading, from the coordinate system of the lab (see LabAxes)
+pub const SCENE_UP: Vec3 = Vec3::Z;
+
+/// Reference vector of an axis of the lab (0 for X, 1 for Y, 2 for Z)
+#[derive(Component)]
+pub(crate) struct ReferenceAxis(usize);
+
 fn setup_environment(
     mut commands: Commands,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -176,14 +230,14 @@ fn setup_environment(
 ) {
     // Base
     commands.spawn((
-        Mesh3d(meshes.add(Plane3d::new(Vec3::Z, [5.0, 5.0].into()))),
+        Mesh3d(meshes.add(Plane3d::new(SCENE_UP, [5.0, 5.0].into()))),
         MeshMaterial3d(materials.add(Color::srgb(0.2, 0.3, 0.2))),
         Transform::from_rotation(Quat::from_rotation_x(0.0)),
     ));
 
     commands.spawn((
         PointLight { ..default() },
-        Transform::from_translation(Vec3::new(0.0, 0.0, 3.0)),
+        Transform::from_translation(SCENE_UP * 3.0),
     ));
 
     commands.insert_resource(AmbientLight {
@@ -244,28 +298,43 @@ fn spawn_reference_vectors(
         Color::srgb_u8(0, 255, 0),
         Color::srgb_u8(0, 0, 255),
     ];
-    let point = Vec3::new(-2.0, -2.0, 0.0);
-    let positions = vec![
-        point + Vec3::new(default_cylinder_height / 2.0, 0.0, 0.0),
-        point + Vec3::new(0.0, default_cylinder_height / 2.0, 0.0),
-        point + Vec3::new(0.0, 0.0, default_cylinder_height / 2.0),
-    ];
-    let rotations = vec![
-        Quat::from_axis_angle(Vec3::Z, -std::f32::consts::FRAC_PI_2),
-        Quat::IDENTITY,
-        Quat::from_axis_angle(Vec3::X, std::f32::consts::FRAC_PI_2),
-    ];
-    colors.iter().zip(rotations).zip(positions).all(|((color, rotation), position)| {
+    for (axis, color) in colors.iter().enumerate() {
         commands.spawn((
             Mesh3d(meshes.add(vector_mesh.clone())),
             MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: color.clone(),
+                base_color: *color,
                 ..default()
             })),
-            Transform::from_translation(position).with_rotation(rotation),
+            reference_axis_transform(&LabAxes::default(), axis),
+            ReferenceAxis(axis),
         ));
-        true
-    });
+    }
+}
+
+/// Transform of the reference vector of an axis of the lab. The vector mesh points to Y, with its base at the center.
+fn reference_axis_transform(lab_axes: &LabAxes, axis: usize) -> Transform {
+    let default_cylinder_height = 0.25;
+    let point = Vec3::new(-2.0, -2.0, 0.0);
+    let mut lab_axis = [0.0; 3];
+    lab_axis[axis] = 1.0;
+    let direction = Vec3::from(lab_axes.to_scene(lab_axis));
+    Transform::from_translation(point + direction * default_cylinder_height / 2.0)
+        .with_rotation(Quat::from_rotation_arc(Vec3::Y, direction))
+}
+
+/// Points the reference vectors (X red, Y green, Z blue) to the axes of the lab of the c3d, in the scene
+fn update_reference_axes(
+    c3d_state: Res<C3dState>,
+    c3d_assets: Res<Assets<C3dAsset>>,
+    mut query: Query<(&ReferenceAxis, &mut Transform)>,
+) {
+    let lab_axes = c3d_assets.get(&c3d_state.handle).map(|asset| asset.lab_axes).unwrap_or_default();
+    for (axis, mut transform) in query.iter_mut() {
+        let new_transform = reference_axis_transform(&lab_axes, axis.0);
+        if *transform != new_transform {
+            *transform = new_transform;
+        }
+    }
 }
 
 fn load_c3d(
@@ -280,10 +349,15 @@ fn load_c3d(
     config_state: Res<ConfigState>,
     config_assets: Res<Assets<ConfigC3dAsset>>,
     query_markers: Query<(Entity, &C3dMarkers)>,
+    query_surfaces: Query<(Entity, &SurfacePatch)>,
+    mut config_validation: ResMut<ConfigValidation>,
+    mut applied_config: ResMut<AppliedConfig>,
+    mut color_maps: ResMut<ColorMapValues>,
 ) {
     if let Some(_) = c3d_events.read().last() {
         
         despawn_all_markers(&mut commands, &query_markers);
+        despawn_all_surfaces(&mut commands, &query_surfaces);
 
         let c3d_asset = c3d_assets.get_mut(&c3d_state.handle);
         let points = 
@@ -294,10 +368,10 @@ fn load_c3d(
                     C3dMarkers  // This is a bunch of markers
                 ))
                 .id();
-        let config_asset = config_assets.get(&config_state.handle); // This contains the literal text of the configuration file.
+        let config_asset = config_assets.get(&config_state.handle);
         let current_config = app_state.current_config.as_deref().unwrap_or("");
         let config_file = match config_asset {
-            Some(asset) => parse_config(&asset.config_str, false).ok(),
+            Some(asset) => Some(&asset.config),
             None => {
                 println!("Config not loaded");
                 None
@@ -307,14 +381,38 @@ fn load_c3d(
         match c3d_asset {
             Some(asset) => {
                 // Spawn markers
-                for label in get_all_labels(&asset.c3d) {
-                    spawn_marker(&label, current_config, &config_file, points, &mut commands, &mut meshes, &mut materials);
+                asset.unit_scale = config_defaults(config_file).get_unit_scale() as f32;
+                // The axes of the interface have priority over the ones of the configuration, and these over the ones of the loader
+                let lab_axes = app_state.lab_axes.or(config_defaults(config_file).get_lab_axes()).unwrap_or(asset.file_lab_axes);
+                asset.set_lab_axes(lab_axes);
+                let labels = get_all_labels(&asset.c3d);
+                for label in &labels {
+                    spawn_marker(label, current_config, config_file, points, &mut commands, &mut meshes, &mut materials);
                 }
 
+                // Virtual points are computed once for every frame, and their markers go after the ones of the c3d
+                let num_frames = asset.c3d.points.size().0;
+                asset.virtual_points = match config_file {
+                    Some(config_file) => {
+                        let mut errors = Vec::new();
+                        let virtual_points = config_file.compute_virtual_points(&labels, num_frames, |frame, i| asset.get_point(frame, i), &mut errors);
+                        for error in errors {
+                            println!("Warning in {}: {}", app_state.config_path, error);
+                        }
+                        virtual_points
+                    }
+                    None => Vec::new(),
+                };
+                for (label, _) in &asset.virtual_points {
+                    spawn_marker(label, current_config, config_file, points, &mut commands, &mut meshes, &mut materials);
+                }
+                config_validation.report = config_file.map(|config_file| config_file.validate(&labels));
+                applied_config.0 = config_file.cloned();
+
                 let current_config = app_state.current_config.clone().unwrap_or_default();
                 app_state.frame_rate = Some(asset.c3d.points.frame_rate);
+                app_state.units = Some(asset.units.clone());
                 
-                let num_frames = asset.c3d.points.size().0;
                 app_state.num_frames = num_frames;
                 app_state.traces.end_frame = num_frames as f32 - 20.0; 
 
@@ -324,9 +422,17 @@ fn load_c3d(
 
                 // Spawn joins
                 if let Some(config_file) = config_file {
-                    spawn_joins_in_config(&current_config, &config_file, &mut commands, &mut meshes, &mut materials);
+                    spawn_joins_in_config(&current_config, config_file, &labels, &mut commands, &mut meshes, &mut materials);
+                    spawn_surfaces_in_config(&current_config, config_file, &labels, &mut commands, &mut meshes, &mut materials);
                 }
 
+                // Color maps use the labels of the markers, virtual points included
+                let marker_labels = labels.iter().cloned().chain(asset.virtual_points.iter().map(|(label, _)| label.clone())).collect::<Vec<_>>();
+                *color_maps = match config_file {
+                    Some(config_file) => compute_color_maps(&current_config, config_file, asset, &marker_labels),
+                    None => ColorMapValues::default(),
+                };
+
                 // Send milestones to the GUI
                 let start_frame = asset.c3d.points.first_frame as f32;
                 for milestone in asset.c3d.events.iter() {
@@ -390,6 +496,7 @@ fn change_frame_rate(
 }
 
 /// Change the configuration of the c3d file. This can be used to change the representation of the c3d file.
+/// If the new configuration has a camera, the orbit camera is moved to it.
 fn change_config(
     mut state: ResMut<AppState>,
     mut commands: Commands,
@@ -398,8 +505,11 @@ fn change_config(
     mut ev_loaded: EventWriter<C3dLoadedEvent>,
     config_state: Res<ConfigState>,
     config_assets: Res<Assets<ConfigC3dAsset>>,
+    c3d_state: Res<C3dState>,
+    c3d_assets: Res<Assets<C3dAsset>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut query_camera: Query<&mut CustomOrbitCamera>,
 ) {
     if !state.change_config{
         return;
@@ -415,7 +525,14 @@ fn change_config(
     despawn_all_vectors(&mut commands, &query_vectors);
     if let Some(config) = config_assets.get(&config_state.handle){
         if let Some(current_config_name) = state.current_config.as_deref() {
-            spawn_vectors_in_config(current_config_name, &config.config, &mut commands, &mut meshes, &mut materials);   
+            let labels = c3d_assets.get(&c3d_state.handle).map(|asset| get_all_labels(&asset.c3d)).unwrap_or_default();
+            spawn_vectors_in_config(current_config_name, &config.config, &labels, &mut commands, &mut meshes, &mut materials);   
+
+            if let Some(camera) = config.config.get_camera(current_config_name) {
+                for mut orbit in query_camera.iter_mut() {
+                    orbit.apply_view(&camera);
+                }
+            }
         }
     }
 }
\ No newline at end of file
@@ -19,55 +19,18 @@ pub enum MarkerEvent {
 pub(crate) fn spawn_marker(
     label: &str,
     current_config: &str,
-    config: &Option<ConfigFile>,
+    config: Option<&ConfigFile>,
     parent: Entity,
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
 ) -> Visibility {
-    let marker_mesh = meshes.add(
-        // Obtain radius from get_point_size
-        Sphere::new(match config.as_ref() {
-            Some(config) => {
-                if let Some(size) = config.get_point_size(label, current_config) {
-                    0.014 * size as f32
-                } else {
-                    0.014
-                }
-            }
-            None => { 0.014 }
-        }).mesh());
+    let marker_mesh = meshes.add(Sphere::new(marker_radius(label, current_config, config)).mesh());
     let marker_material = materials.add(StandardMaterial {
-        // Obtain color from get_point_color
-        base_color: match config.as_ref() {
-            Some(config) => {
-                if let Some(color) = config.get_point_color(label, current_config){
-                    if color.len() == 3 {
-                        Color::srgb_u8(color[0], color[1], color[2])
-                    } else if color.len() == 4 {
-                        Color::srgba_u8(color[0], color[1], color[2], color[3])
-                    } else {
-                        Color::srgb(0.0, 0.0, 1.0)
-                    }
-                } else {
-                    Color::srgb(0.0, 0.0, 1.0)
-                }
-            }
-            None => { Color::srgb(0.0, 0.0, 1.0) }
-        },
+        base_color: marker_color(label, current_config, config),
         ..default()
     });
-
-    let marker_visibility = match config.as_ref() {
-        Some(config) => {
-            if config.contains_point_regex(current_config, label) {
-                Visibility::Visible
-            } else {
-                Visibility::Hidden
-            }
-        }
-        None => { Visibility::Visible }
-    };
+    let marker_visibility = marker_visibility(label, current_config, config);
     
     commands.spawn((
         Mesh3d(marker_mesh),
@@ -79,6 +42,35 @@ pub(crate) fn spawn_marker(
     marker_visibility
 }
 
+/// Radius of the sphere of a marker, obtained from get_point_size
+pub(crate) fn marker_radius(label: &str, current_config: &str, config: Option<&ConfigFile>) -> f32 {
+    let defaults = config_defaults(config);
+    let size = config.and_then(|config| config.get_point_size(label, current_config)).unwrap_or(defaults.get_point_size());
+    (defaults.get_marker_radius() * size) as f32
+}
+
+/// Color of a marker, obtained from get_point_color
+pub(crate) fn marker_color(label: &str, current_config: &str, config: Option<&ConfigFile>) -> Color {
+    let color = config.and_then(|config| config.get_point_color(label, current_config)).unwrap_or_else(|| config_defaults(config).get_point_color());
+    Color::from(color)
+}
+
+/// Built-in values of `[defaults]`, used without configuration
+static BUILT_IN_DEFAULTS: std::sync::LazyLock<Defaults> = std::sync::LazyLock::new(Defaults::default);
+
+/// Values of `[defaults]` of the configuration, or the built-in ones without configuration
+pub(crate) fn config_defaults(config: Option<&ConfigFile>) -> &Defaults {
+    config.map_or(&BUILT_IN_DEFAULTS, |config| config.get_defaults())
+}
+
+/// Visibility of a marker in the configuration. Without configuration, every marker is visible.
+pub(crate) fn marker_visibility(label: &str, current_config: &str, config: Option<&ConfigFile>) -> Visibility {
+    match config {
+        Some(config) if !config.contains_point_regex(current_config, label) => Visibility::Hidden,
+        _ => Visibility::Visible,
+    }
+}
+
 pub(crate) fn represent_points(
     mut state: ResMut<AppState>,
     query_points: Query<(&C3dMarkers, &Children)>,          // C3dMarkers and their children (Markers)
@@ -103,9 +95,7 @@ pub(crate) fn represent_points(
                     let pos = query_markers.get_mut(child);
                     match pos {
                         Ok((mut transform, mut vis, marker)) => {
-                            let x = point_data[(state.frame, i)][0] as f32 / 1000.0;
-                            let y = point_data[(state.frame, i)][1] as f32 / 1000.0;
-                            let z = point_data[(state.frame, i)][2] as f32 / 1000.0;
+                            let [x, y, z] = c3d.get_point_in_metres(state.frame, i);
                             
                             if x == 0.0 && y == 0.0 && z == 0.0 {
                                 *vis = Visibility::Hidden;
@@ -184,11 +174,7 @@ pub fn get_marker_position_on_frame_range(
                         return;
                     }
                     for frame in start_frame..end_frame {
-                        positions.push(Vec3::new(
-                            point_data[(frame, i)][0] as f32 / 1000.0, // frame, point_idx, x/y/z
-                            point_data[(frame, i)][1] as f32 / 1000.0,
-                            point_data[(frame, i)][2] as f32 / 1000.0,
-                        ));
+                        positions.push(Vec3::from(asset.get_point_in_metres(frame, i)));      // frame, point_idx
                     }
                 }
                 i += 1;
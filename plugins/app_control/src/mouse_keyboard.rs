@@ -1,5 +1,7 @@
 use bevy::input::mouse::{MouseMotion, MouseWheel};
 
+use config_plugin::CameraView;
+
 use crate::*;
 
 #[derive(Component)]
@@ -10,6 +12,23 @@ pub(crate) struct CustomOrbitCamera {
     pub pitch: f32,
 }
 
+impl CustomOrbitCamera {
+    /// Moves the camera to a view of the configuration. The fields that the view does not set are kept.
+    pub(crate) fn apply_view(&mut self, view: &CameraView) {
+        if let Some(center) = view.center {
+            self.center = Vec3::new(center[0] as f32, center[1] as f32, center[2] as f32);
+        }
+        if let Some(distance) = view.distance {
+            self.distance = distance as f32;
+        }
+        if let Some(yaw) = view.yaw {
+            self.yaw = (yaw as f32).to_radians();
+        }
+        if let Some(pitch) = view.pitch {
+            self.pitch = (pitch as f32).to_radians().clamp(-std::f32::consts::FRAC_PI_2, 0.0);
+        }
+    }
+}
 
 pub fn keyboard_controls (
     keyboard: Res<ButtonInput<KeyCode>>,
@@ -38,8 +57,8 @@ pub fn keyboard_controls (
                 // TODO: Implement a way to stop the program    
             }
             KeyCode::F5 => {
-                println!("Reloading assets");
-                asset_server.reload(state.c3d_path.clone());
+                // The changes are applied by apply_config_changes, keeping the frame, the traces and the graphs
+                println!("Reloading config");
                 asset_server.reload(state.config_path.clone());
             }
             KeyCode::F6 => {
@@ -56,39 +75,31 @@ pub fn keyboard_controls (
             KeyCode::AltLeft => {
                 state.render_at_fixed_frame_rate = !state.render_at_fixed_frame_rate;
             }
-            KeyCode::Numpad1 | KeyCode::Digit1 => {
-                let config_state = config_assets.get(&config_state.handle);
-                if let Some(config_state) = config_state {
-                    state.current_config = config_state
-                        .config
-                        .get_all_config_names()
-                        .first()
-                        .cloned(); 
-                    state.change_config = true;
-                    println!("First config: {:?}", state.current_config);
-                }                
-            }
-            
+            KeyCode::Numpad1 | KeyCode::Digit1 |
             KeyCode::Numpad2 | KeyCode::Digit2 |
             KeyCode::Numpad3 | KeyCode::Digit3 |
             KeyCode::Numpad4 | KeyCode::Digit4 |
             KeyCode::Numpad5 | KeyCode::Digit5 |
             KeyCode::Numpad6 | KeyCode::Digit6 |
             KeyCode::Numpad7 | KeyCode::Digit7 |
-            KeyCode::Numpad8 | KeyCode::Digit8 => {
+            KeyCode::Numpad8 | KeyCode::Digit8 |
+            KeyCode::Numpad9 | KeyCode::Digit9 => {
                 let config_state = config_assets.get(&config_state.handle);
                 if let Some(config_state) = config_state {
-                    let idx = match key {
-                        KeyCode::Numpad2 | KeyCode::Digit2 => 1,
-                        KeyCode::Numpad3 | KeyCode::Digit3 => 2,
-                        KeyCode::Numpad4 | KeyCode::Digit4 => 3,
-                        KeyCode::Numpad5 | KeyCode::Digit5 => 4,
-                        KeyCode::Numpad6 | KeyCode::Digit6 => 5,
-                        KeyCode::Numpad7 | KeyCode::Digit7 => 6,
-                        KeyCode::Numpad8 | KeyCode::Digit8 => 7,
+                    let digit = match key {
+                        KeyCode::Numpad1 | KeyCode::Digit1 => 1,
+                        KeyCode::Numpad2 | KeyCode::Digit2 => 2,
+                        KeyCode::Numpad3 | KeyCode::Digit3 => 3,
+                        KeyCode::Numpad4 | KeyCode::Digit4 => 4,
+                        KeyCode::Numpad5 | KeyCode::Digit5 => 5,
+                        KeyCode::Numpad6 | KeyCode::Digit6 => 6,
+                        KeyCode::Numpad7 | KeyCode::Digit7 => 7,
+                        KeyCode::Numpad8 | KeyCode::Digit8 => 8,
+                        KeyCode::Numpad9 | KeyCode::Digit9 => 9,
                         _ => unreachable!(),
                     };
-                    let get_config = get_config_index(config_state, idx);
+                    // By the hotkey of the configurations. The free digits select the configurations without hotkey, by position
+                    let get_config = config_state.config.get_config_for_hotkey(digit);
                     if get_config.is_none() {
                         return;
                     }
@@ -98,19 +109,6 @@ pub fn keyboard_controls (
                     }
                 }
             }
-
-            KeyCode::Numpad9 | KeyCode::Digit9 => {
-                let config_state = config_assets.get(&config_state.handle);
-                if let Some(config_state) = config_state {
-                    state.current_config = config_state
-                        .config
-                        .get_all_config_names()
-                        .last()
-                        .cloned();
-                    state.change_config = true;
-                    println!("Last config: {:?}", state.current_config);
-                }
-            }
             _ => {}
         }
     }    
@@ -150,10 +148,10 @@ pub(crate) fn update_orbit_camera(
     orbit.yaw += delta_yaw;
     orbit.pitch = (orbit.pitch + delta_pitch).clamp(-std::f32::consts::FRAC_PI_2, 0.0);
 
-    let base_rotation = Quat::from_xyzw(std::f32::consts::SQRT_2 / 2.0, 0.0, 0.0, std::f32::consts::SQRT_2 / 2.0).normalize();
+    // The camera looks along -Z with Y up, so it is turned to the up axis of the scene
+    let base_rotation = Quat::from_rotation_arc(Vec3::Y, SCENE_UP);
 
-    let corrected_up = base_rotation * Vec3::Y; 
-    let yaw_rotation = Quat::from_axis_angle(corrected_up, orbit.yaw);
+    let yaw_rotation = Quat::from_axis_angle(SCENE_UP, orbit.yaw);
     let pitch_rotation = Quat::from_axis_angle(Vec3::X, orbit.pitch);
 
     let final_rotation = yaw_rotation * base_rotation * pitch_rotation;
@@ -170,10 +168,3 @@ pub(crate) fn update_orbit_camera(
     transform.rotation = final_rotation;
 }
 
-fn get_config_index(c3d_asset: &ConfigC3dAsset, idx: usize) -> Option<String> {
-    if c3d_asset.config.get_all_config_names().len() > idx {
-        Some(c3d_asset.config.get_all_config_names()[idx].clone())
-    } else {
-        None
-    }
-}
\ No newline at end of file
@@ -0,0 +1,91 @@
+use bevy::render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages};
+
+use config_plugin::ConfigColor;
+
+use crate::*;
+
+#[derive(Component, Clone, PartialEq)]
+/// Polygon filled between markers. It contains the labels of its points, in order.
+pub struct SurfacePatch(pub(crate) Vec<String>);
+
+/// Spawns the surfaces of the configuration. The points are resolved to the labels of the C3D (see ConfigFile::resolve_label).
+/// Their mesh is built every frame by represent_surfaces.
+pub(crate) fn spawn_surfaces_in_config(
+    current_config: &str,
+    config_file: &ConfigFile,
+    labels: &[String],
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(surfaces) = config_file.get_config(current_config).and_then(|config| config.get_surfaces()) else {
+        return;
+    };
+    for surface in surfaces {
+        let color = Color::from(surface.color.unwrap_or(ConfigColor::rgba(180, 180, 180, 128)));
+        let material = materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode: if color.alpha() < 1.0 { AlphaMode::Blend } else { AlphaMode::Opaque },
+            double_sided: true,
+            cull_mode: None,
+            ..default()
+        });
+        let points = surface.points.iter().map(|point| config_file.resolve_label(point, labels)).collect();
+        commands.spawn((
+            Mesh3d(meshes.add(polygon_mesh(&[]))),
+            MeshMaterial3d(material),
+            SurfacePatch(points),
+        ));
+    }
+}
+
+/// Rebuilds the polygon of every surface with the positions of its markers in the current frame.
+/// Markers missing in the frame are left out, and a surface with less than 3 points is not shown.
+pub(crate) fn represent_surfaces(
+    markers_query: Query<(&Marker, &Transform)>,
+    surfaces_query: Query<(&SurfacePatch, &Mesh3d)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (surface, mesh) in surfaces_query.iter() {
+        let points = surface.0.iter()
+            .filter_map(|label| get_marker_position_on_frame(label, &markers_query))
+            .filter(|point| *point != Vec3::ZERO)
+            .collect::<Vec<_>>();
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            *mesh = polygon_mesh(&points);
+        }
+    }
+}
+
+/// Triangle fan around the centroid of the points, so polygons that are not flat or not convex are still filled.
+/// The normal of every vertex is the one of the whole polygon (Newell's method).
+fn polygon_mesh(points: &[Vec3]) -> Mesh {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+    if points.len() > 2 {
+        let centroid = points.iter().sum::<Vec3>() / points.len() as f32;
+        let normal = points.iter().zip(points.iter().cycle().skip(1))
+            .fold(Vec3::ZERO, |normal, (a, b)| normal + (*a - centroid).cross(*b - centroid))
+            .normalize_or(Vec3::Z);
+        positions.push(centroid.to_array());
+        positions.extend(points.iter().map(|point| point.to_array()));
+        normals.resize(positions.len(), normal.to_array());
+        for i in 0..points.len() {
+            indices.extend([0, i as u32 + 1, ((i + 1) % points.len()) as u32 + 1]);
+        }
+    }
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+pub(crate) fn despawn_all_surfaces(
+    commands: &mut Commands,
+    query_surfaces: &Query<(Entity, &SurfacePatch)>,
+) {
+    for (entity, _) in query_surfaces.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
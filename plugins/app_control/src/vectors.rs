@@ -36,19 +36,28 @@ pub enum VectorEvent {
     ShowVectorEvent(Vector),    
 }
 
+/// Spawns the vectors of the configuration. The points and vectors are resolved to the labels of the C3D (see ConfigFile::resolve_label).
 pub(crate) fn spawn_vectors_in_config(
     current_config: &str,
     config_file: &ConfigFile,
+    labels: &[String],
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
 ){
+    let defaults = config_file.get_defaults();
     if config_file.get_config(current_config).is_some(){
         if let Some(vectors_map) = config_file.get_config(current_config).unwrap().get_vectors(){
             for (point, vectors) in vectors_map {
+                let point = &config_file.resolve_label(point, labels);
                 let num_vectors = vectors.len();
-                let mut i = 0;
-                for (vector, scale) in vectors {
+                for (i, (vector, scale)) in vectors.iter().enumerate() {
+                    let vector = &config_file.resolve_label(vector, labels);
+                    // The 3 vectors of a point are its axes
+                    let color = match num_vectors {
+                        3 => Color::from(defaults.get_vector_colors()[i]),
+                        _ => Color::from(defaults.get_vector_color()),
+                    };
                     let default_cylinder_height = 1.0;
                     let mut cone_mesh = Mesh::from(Cone {
                         radius: if num_vectors == 3 {0.025} else {0.05}, // Adapt possitional vectors
@@ -77,35 +86,14 @@ pub(crate) fn spawn_vectors_in_config(
                     commands.spawn((
                         Mesh3d(meshes.add(cylinder_mesh)),
                         MeshMaterial3d(materials.add(StandardMaterial {
-                        base_color: {
-                            if num_vectors == 3 && i == 0 {
-                                Color::srgb_u8(255, 0, 0)
-                            } else if num_vectors == 3 && i == 1 {
-                                Color::srgb_u8(0, 255, 0)
-                            } else if num_vectors == 3 && i == 2 {
-                                Color::srgb_u8(0, 0, 255)
-                            } else {
-                                Color::srgb_u8(255, 220, 0)
-                            }},
+                            base_color: color,
                             ..default()})),
                         Transform::from_translation(Vec3::new(0.0, 0.5, 0.0)),
                         Vector(Marker(point.clone(), Visibility::Visible), Marker(vector.clone(), Visibility::Visible), *scale)));
                     commands.spawn((
                         Mesh3d(meshes.add(cone_mesh)),
                         MeshMaterial3d(materials.add(StandardMaterial {
-                            base_color: {
-                                if num_vectors == 3 && i == 0 {
-                                    i += 1;
-                                    Color::srgb_u8(255, 0, 0)
-                                } else if num_vectors == 3 && i == 1 {
-                                    i += 1;
-                                    Color::srgb_u8(0, 255, 0)
-                                } else if num_vectors == 3 && i == 2 {
-                                    i += 1;
-                                    Color::srgb_u8(0, 0, 255)
-                                } else {
-                                    Color::srgb_u8(255, 220, 0)
-                                }},
+                            base_color: color,
                             ..default()
                         })),
                         Transform::from_translation(Vec3::new(0.0, *scale as f32, 0.0)),
@@ -3,15 +3,26 @@ use bevy_asset::{
 };
 use bevy_ecs::prelude::{Event, EventWriter, ResMut, Resource};
 use bevy_reflect::TypePath;
-use c3dio::{C3d, C3dParseError};
+use c3dio::{C3d, C3dParseError, ParameterData};
+use config_plugin::LabAxes;
+use serde::{Deserialize, Serialize};
 
 /// Loader for C3D files
 #[derive(Default)]
 pub struct C3dLoader;
 
+/// Settings of the C3D loader, for AssetServer::load_with_settings (or the `.meta` file of the C3D)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct C3dLoaderSettings {
+    /// Units of the points ("mm", "cm", "m"...), used instead of POINT:UNITS. For files without units, or with wrong ones.
+    pub units: Option<String>,
+    /// Coordinate system of the lab (up and forward axes, and handedness). Right-handed with Z up by default.
+    pub lab_axes: Option<LabAxes>,
+}
+
 impl AssetLoader for C3dLoader {
     type Asset = C3dAsset;
-    type Settings = ();
+    type Settings = C3dLoaderSettings;
     type Error = C3dParseError;
 
     /// The most convenient way to load C3D files is to read the entire file into memory
@@ -19,7 +30,7 @@ impl AssetLoader for C3dLoader {
     async fn load<'a>(
         &self,
         reader: &mut dyn Reader,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
         load_context: &mut LoadContext<'_>,
     ) -> Result<C3dAsset, C3dParseError> {
         let mut bytes = Vec::new();
@@ -27,7 +38,7 @@ impl AssetLoader for C3dLoader {
         if let Err(err) = res {
             return Err(C3dParseError::ReadError(err));
         }
-        load_c3d(bytes.as_slice(), load_context).await
+        load_c3d(bytes.as_slice(), settings, load_context).await
     }
 
     /// C3D files have the extension "c3d"
@@ -38,18 +49,69 @@ impl AssetLoader for C3dLoader {
     }
 }
 
+/// Parses the C3D and converts its points to millimetres, from the units of the settings or POINT:UNITS, and to the
+/// coordinate system of the scene, from the lab_axes of the settings.
+/// Files without units are assumed to be in millimetres. The scale of POINT:SCALE is already applied by c3dio.
 async fn load_c3d<'a, 'b>(
     bytes: &'a [u8],
-    _load_context: &'a mut LoadContext<'b>,
+    settings: &'a C3dLoaderSettings,
+    load_context: &'a mut LoadContext<'b>,
 ) -> Result<C3dAsset, C3dParseError> {
     let c3d = C3d::from_bytes(bytes);
-    let c3d = match c3d {
+    let mut c3d = match c3d {
         Ok(c3d) => c3d,
         Err(err) => {
             return Err(err);
         }
     };
-    Ok(C3dAsset { c3d })
+    let units = settings.units.clone()
+        .or_else(|| point_units(&c3d))
+        .unwrap_or_else(|| "mm".to_string());
+    let scale = millimetres_per_unit(&units).unwrap_or_else(|| {
+        println!("Unknown units \"{}\" in {}. Assuming millimetres", units, load_context.path().display());
+        1.0
+    });
+    if scale != 1.0 {
+        let (num_frames, num_points) = c3d.points.size();
+        for frame in 0..num_frames {
+            for i in 0..num_points {
+                for coordinate in c3d.points[(frame, i)].iter_mut() {
+                    *coordinate *= scale;
+                }
+            }
+        }
+    }
+    let file_lab_axes = settings.lab_axes.unwrap_or_default();
+    if !file_lab_axes.is_valid() {
+        println!("The up and forward axes of {} are along the same axis. Using {} forward", load_context.path().display(), file_lab_axes.or_with_up().forward.name());
+    }
+    let file_lab_axes = file_lab_axes.or_with_up();
+    let mut asset = C3dAsset { c3d, virtual_points: Vec::new(), unit_scale: 0.001, units, lab_axes: LabAxes::default(), file_lab_axes };
+    asset.set_lab_axes(file_lab_axes);
+    Ok(asset)
+}
+
+/// Value of POINT:UNITS, if the file has it
+fn point_units(c3d: &C3d) -> Option<String> {
+    let parameter = c3d.parameters.get_group("POINT")?.get("UNITS")?;
+    match &parameter.data {
+        ParameterData::Char(units) => Some(units.iter().collect::<String>().trim().to_string()).filter(|units| !units.is_empty()),
+        _ => None,
+    }
+}
+
+/// Millimetres in one unit of POINT:UNITS. None if the units are not known.
+pub fn millimetres_per_unit(units: &str) -> Option<f32> {
+    match units.trim().to_lowercase().as_str() {
+        "um" | "µm" | "micrometre" | "micrometres" | "micrometer" | "micrometers" => Some(0.001),
+        "mm" | "millimetre" | "millimetres" | "millimeter" | "millimeters" => Some(1.0),
+        "cm" | "centimetre" | "centimetres" | "centimeter" | "centimeters" => Some(10.0),
+        "dm" | "decimetre" | "decimetres" | "decimeter" | "decimeters" => Some(100.0),
+        "m" | "metre" | "metres" | "meter" | "meters" => Some(1000.0),
+        "in" | "inch" | "inches" => Some(25.4),
+        "ft" | "foot" | "feet" => Some(304.8),
+        _ => None,
+    }
 }
 
 /// State for loading C3D files
@@ -68,6 +130,18 @@ pub struct C3dState {
 #[type_path = "bevy_c3d::c3d_loader::C3dAsset"]
 pub struct C3dAsset {
     pub c3d: C3d,
+    /// Trajectories of the virtual points of the configuration, with their label, in millimetres.
+    /// They are indexed after the points of the C3D (see get_point).
+    pub virtual_points: Vec<(String, Vec<[f32; 3]>)>,
+    /// Factor from millimetres (the units of the points once loaded) to metres. 0.001 by default, the configuration can
+    /// change it.
+    pub unit_scale: f32,
+    /// Units of the points in the file (POINT:UNITS, or the ones of C3dLoaderSettings). They are converted to millimetres.
+    pub units: String,
+    /// Coordinate system of the lab that the points have been converted from. Use set_lab_axes to change it.
+    pub lab_axes: LabAxes,
+    /// Coordinate system of the lab of C3dLoaderSettings, used when the application does not set another one
+    pub file_lab_axes: LabAxes,
 }
 
 impl C3dAsset {
@@ -80,7 +154,47 @@ impl C3dAsset {
             }
         });
     }
-    
+
+    /// Position of the point with index i in a frame, in millimetres, in the coordinate system of the scene.
+    /// The indexes after the points of the C3D are the virtual points, in order.
+    pub fn get_point(&self, frame: usize, i: usize) -> [f32; 3] {
+        let num_points = self.c3d.points.size().1;
+        if i < num_points {
+            self.c3d.points[(frame, i)]
+        } else {
+            self.virtual_points
+                .get(i - num_points)
+                .and_then(|(_, trajectory)| trajectory.get(frame))
+                .copied()
+                .unwrap_or([0.0; 3])
+        }
+    }
+
+    /// Converts the points, and the virtual points, to the scene from another coordinate system of the lab
+    pub fn set_lab_axes(&mut self, lab_axes: LabAxes) {
+        let old_lab_axes = self.lab_axes;
+        if lab_axes == old_lab_axes {
+            return;
+        }
+        let convert = |point: [f32; 3]| lab_axes.to_scene(old_lab_axes.to_lab(point));
+        let (num_frames, num_points) = self.c3d.points.size();
+        for frame in 0..num_frames {
+            for i in 0..num_points {
+                self.c3d.points[(frame, i)] = convert(self.c3d.points[(frame, i)]);
+            }
+        }
+        for (_, trajectory) in self.virtual_points.iter_mut() {
+            for point in trajectory.iter_mut() {
+                *point = convert(*point);
+            }
+        }
+        self.lab_axes = lab_axes;
+    }
+
+    /// Position of the point with index i in a frame, in metres (see get_point and unit_scale)
+    pub fn get_point_in_metres(&self, frame: usize, i: usize) -> [f32; 3] {
+        self.get_point(frame, i).map(|coordinate| coordinate * self.unit_scale)
+    }
 }
 
 /// Event that is sent when a C3D file is loaded
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+/// Axis of the coordinate system of the lab, with its direction
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+    NegX,
+    NegY,
+    NegZ,
+}
+
+impl Axis {
+    /// Parses "x", "+x" or "-x" (in any case)
+    pub fn from_name(name: &str) -> Option<Axis> {
+        match name.trim().to_lowercase().as_str() {
+            "x" | "+x" => Some(Axis::X),
+            "y" | "+y" => Some(Axis::Y),
+            "z" | "+z" => Some(Axis::Z),
+            "-x" => Some(Axis::NegX),
+            "-y" => Some(Axis::NegY),
+            "-z" => Some(Axis::NegZ),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Axis::X => "+X",
+            Axis::Y => "+Y",
+            Axis::Z => "+Z",
+            Axis::NegX => "-X",
+            Axis::NegY => "-Y",
+            Axis::NegZ => "-Z",
+        }
+    }
+
+    /// Index of the coordinate of the axis (0 for X, 1 for Y, 2 for Z)
+    pub fn index(&self) -> usize {
+        match self {
+            Axis::X | Axis::NegX => 0,
+            Axis::Y | Axis::NegY => 1,
+            Axis::Z | Axis::NegZ => 2,
+        }
+    }
+
+    fn sign(&self) -> f32 {
+        match self {
+            Axis::X | Axis::Y | Axis::Z => 1.0,
+            Axis::NegX | Axis::NegY | Axis::NegZ => -1.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+/// Coordinate system of the lab where the C3D was recorded: which of its axes points up and which forward, and whether
+/// it is right or left-handed. The points are converted to the coordinate system of the scene, that is right-handed with
+/// Z up and X forward (the default lab).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LabAxes {
+    pub up: Axis,
+    pub forward: Axis,
+    pub handedness: Handedness,
+}
+
+impl Default for LabAxes {
+    fn default() -> Self {
+        LabAxes { up: Axis::Z, forward: Axis::X, handedness: Handedness::Right }
+    }
+}
+
+impl LabAxes {
+    /// Right-handed lab with the given axis up, and the first of the other axes (X, Y, Z) forward
+    pub fn with_up(up: Axis) -> Self {
+        let forward = if up.index() == 0 { Axis::Y } else { Axis::X };
+        LabAxes { up, forward, handedness: Handedness::Right }
+    }
+
+    /// The up and forward axes must be different
+    pub fn is_valid(&self) -> bool {
+        self.up.index() != self.forward.index()
+    }
+
+    /// These axes if they are valid. Otherwise, the ones of with_up with the same up axis and handedness.
+    pub fn or_with_up(self) -> Self {
+        if self.is_valid() {
+            self
+        } else {
+            LabAxes { handedness: self.handedness, ..LabAxes::with_up(self.up) }
+        }
+    }
+
+    /// Axis of the lab that goes to the Y axis of the scene (up × forward in a right-handed lab), with its sign
+    fn side(&self) -> (usize, f32) {
+        let (up, forward) = (self.up.index(), self.forward.index());
+        let cyclic = if (up + 1) % 3 == forward { 1.0 } else { -1.0 };
+        let handedness = match self.handedness {
+            Handedness::Right => 1.0,
+            Handedness::Left => -1.0,
+        };
+        (3 - up - forward, self.up.sign() * self.forward.sign() * cyclic * handedness)
+    }
+
+    /// Position in the scene of a point of the lab
+    pub fn to_scene(&self, point: [f32; 3]) -> [f32; 3] {
+        let (side, side_sign) = self.side();
+        [
+            point[self.forward.index()] * self.forward.sign(),
+            point[side] * side_sign,
+            point[self.up.index()] * self.up.sign(),
+        ]
+    }
+
+    /// Position in the lab of a point of the scene (the inverse of to_scene)
+    pub fn to_lab(&self, point: [f32; 3]) -> [f32; 3] {
+        let (side, side_sign) = self.side();
+        let mut lab = [0.0; 3];
+        lab[self.forward.index()] = point[0] * self.forward.sign();
+        lab[side] = point[1] * side_sign;
+        lab[self.up.index()] = point[2] * self.up.sign();
+        lab
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_to_the_scene_and_back() {
+        let lab_axes = LabAxes { up: Axis::Y, forward: Axis::NegZ, handedness: Handedness::Left };
+        let point = [1.0, 2.0, 3.0];
+        assert_eq!(lab_axes.to_scene(point), [-3.0, 1.0, 2.0]);
+        assert_eq!(lab_axes.to_lab(lab_axes.to_scene(point)), point);
+    }
+
+    #[test]
+    fn invalid_axes_fall_back_to_with_up() {
+        let lab_axes = LabAxes { up: Axis::Z, forward: Axis::NegZ, handedness: Handedness::Left };
+        assert!(!lab_axes.is_valid());
+        assert_eq!(lab_axes.or_with_up(), LabAxes { up: Axis::Z, forward: Axis::X, handedness: Handedness::Left });
+        assert_eq!(LabAxes::default().or_with_up(), LabAxes::default());
+    }
+}
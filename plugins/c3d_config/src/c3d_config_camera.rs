@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use toml::{Value, map::Map};
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::{parse_float, ConfigFile};
+
+/// View of the orbit camera. The fields that are None keep the current value of the camera.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CameraView {
+    /// Name of a view of `[views]`. The other fields of the camera are applied on top of it
+    pub view: Option<String>,
+    /// Point the camera looks at, in metres
+    pub center: Option<[f64; 3]>,
+    /// Distance from the camera to the center, in metres
+    pub distance: Option<f64>,
+    /// Rotation around the vertical axis, in degrees
+    pub yaw: Option<f64>,
+    /// Rotation above the horizontal, in degrees. 0 is horizontal and -90 looks from above
+    pub pitch: Option<f64>,
+}
+
+impl CameraView {
+    /// This view, with the fields it does not define taken from `defaults`
+    pub fn with_defaults(&self, defaults: &CameraView) -> CameraView {
+        CameraView {
+            view: self.view.clone().or(defaults.view.clone()),
+            center: self.center.or(defaults.center),
+            distance: self.distance.or(defaults.distance),
+            yaw: self.yaw.or(defaults.yaw),
+            pitch: self.pitch.or(defaults.pitch),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Named views of `[views]`, that the configurations can use in their camera
+    pub fn get_views(&self) -> Option<&HashMap<String, CameraView>> {
+        self.views.as_ref()
+    }
+
+    pub fn get_view(&self, name: &str) -> Option<&CameraView> {
+        self.views.as_ref().and_then(|views| views.get(name))
+    }
+
+    pub fn add_view(&mut self, name: String, view: CameraView) {
+        self.views.get_or_insert_with(HashMap::new).insert(name, view);
+    }
+
+    /// Camera of a configuration, with its named view resolved: the fields of the camera of the configuration are applied
+    /// on top of the view. None if the configuration has no camera.
+    pub fn get_camera(&self, config: &str) -> Option<CameraView> {
+        let camera = self.get_config(config)?.get_camera()?;
+        let view = camera.view.as_deref().and_then(|view| self.get_view(view)).cloned().unwrap_or_default();
+        Some(camera.with_defaults(&view))
+    }
+}
+
+/// Parses the `[views]` table. Each view is a table with the fields of a camera, except `view`.
+pub(crate) fn parse_views(table: &Map<String, Value>, errors: &mut Vec<ConfigError>) -> HashMap<String, CameraView> {
+    let mut views = HashMap::new();
+    for (name, value) in table {
+        let path = vec!["views".into(), name.into()];
+        let Value::Table(view_table) = value else {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected a table with the view, found {}", value.type_str())));
+            continue;
+        };
+        if view_table.contains_key("view") {
+            errors.push(ConfigError::new(
+                ConfigErrorKind::InvalidValue,
+                [path.clone(), vec!["view".into()]].concat(),
+                "A view cannot use another view. Ignoring it",
+            ));
+        }
+        let mut view = parse_camera_table(view_table, path, errors);
+        view.view = None;
+        views.insert(name.clone(), view);
+    }
+    views
+}
+
+/// Parses the camera of a configuration: the name of a view, or a table with the fields of CameraView.
+/// A view that is not in `views` is reported and ignored, and the camera keeps its own fields (if it has any).
+pub(crate) fn parse_camera(
+    value: &Value,
+    views: &Option<HashMap<String, CameraView>>,
+    path: Vec<ConfigKey>,
+    errors: &mut Vec<ConfigError>,
+) -> Option<CameraView> {
+    let mut camera = match value {
+        Value::String(view) => CameraView { view: Some(view.clone()), ..Default::default() },
+        Value::Table(camera) => parse_camera_table(camera, path.clone(), errors),
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected the name of a view or a camera table, found {}", value.type_str())));
+            return None;
+        }
+    };
+    if let Some(view) = camera.view.as_ref().filter(|view| !views.as_ref().is_some_and(|views| views.contains_key(*view))) {
+        errors.push(ConfigError::new(ConfigErrorKind::UnknownReference, path, format!("View {:?} not found in [views]. Ignoring it", view)));
+        camera.view = None;
+    }
+    (camera != CameraView::default()).then_some(camera)
+}
+
+fn parse_camera_table(table: &Map<String, Value>, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> CameraView {
+    let key_path = |key: &str| [path.clone(), vec![key.into()]].concat();
+    let view = match table.get("view") {
+        Some(Value::String(view)) => Some(view.clone()),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("view"), format!("Expected the name of a view, found {}", other.type_str())));
+            None
+        }
+        None => None,
+    };
+    let center = table.get("center").and_then(|center| match center {
+        Value::Array(coordinates) if coordinates.len() == 3 => {
+            let coordinates = coordinates.iter().enumerate()
+                .map(|(i, c)| parse_float(c, [key_path("center"), vec![i.into()]].concat(), errors))
+                .collect::<Option<Vec<_>>>()?;
+            Some([coordinates[0], coordinates[1], coordinates[2]])
+        }
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("center"), "Expected the center as [x, y, z]"));
+            None
+        }
+    });
+    CameraView {
+        view,
+        center,
+        distance: table.get("distance").and_then(|v| parse_float(v, key_path("distance"), errors)),
+        yaw: table.get("yaw").and_then(|v| parse_float(v, key_path("yaw"), errors)),
+        pitch: table.get("pitch").and_then(|v| parse_float(v, key_path("pitch"), errors)),
+    }
+}
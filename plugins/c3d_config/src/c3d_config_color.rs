@@ -0,0 +1,221 @@
+use bevy::color::Color;
+use serde::Deserialize;
+use toml::Value;
+
+use crate::c3d_config_error::*;
+
+/// Color of the configuration, in sRGB with components between 0 and 255. Colors without alpha are opaque (alpha 255).
+///
+/// In the file, a color can be written as:
+/// - An array of 3 (RGB) or 4 (RGBA) integers between 0 and 255: `[255, 136, 0]`.
+/// - An array of 3 or 4 numbers between 0 and 1, if any of them has decimals: `[1.0, 0.53, 0.0]`.
+/// - A hexadecimal string: `"#ff8800"`, `"#ff880080"`, or the short forms `"#f80"` and `"#f808"`.
+/// - The name of a CSS color: `"orange"`, `"steelblue"`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfigColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl ConfigColor {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        ConfigColor { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        ConfigColor { r, g, b, a }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.a == 255
+    }
+
+    /// Components of the color, without the alpha if it is opaque. It is the form the color is written in (see ConfigFile::to_toml_string).
+    pub fn components(&self) -> Vec<u8> {
+        match self.is_opaque() {
+            true => vec![self.r, self.g, self.b],
+            false => vec![self.r, self.g, self.b, self.a],
+        }
+    }
+
+    /// Color of a hexadecimal string, with or without `#`: `rgb`, `rgba`, `rrggbb` or `rrggbbaa`
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+        let digits = match hex.len() {
+            3 | 4 => hex.chars().map(|c| u8::from_str_radix(&c.to_string(), 16).ok().map(|d| d * 17)).collect::<Option<Vec<_>>>()?,
+            6 | 8 => (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect::<Option<Vec<_>>>()?,
+            _ => return None,
+        };
+        Some(ConfigColor::rgba(digits[0], digits[1], digits[2], digits.get(3).copied().unwrap_or(255)))
+    }
+
+    /// Color of a CSS color name. Case, spaces, `-` and `_` are ignored (`"Steel Blue"` is `steelblue`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.chars().filter(|c| !matches!(c, ' ' | '-' | '_')).collect::<String>().to_lowercase();
+        NAMED_COLORS.iter().find(|(color_name, _)| *color_name == name).map(|(_, [r, g, b])| ConfigColor::rgb(*r, *g, *b))
+    }
+}
+
+impl From<ConfigColor> for Color {
+    fn from(color: ConfigColor) -> Self {
+        Color::srgba_u8(color.r, color.g, color.b, color.a)
+    }
+}
+
+/// Parses a color in any of the forms of ConfigColor. Invalid colors are reported and ignored.
+pub(crate) fn parse_color(value: &Value, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<ConfigColor> {
+    let components = match value {
+        Value::String(color) => {
+            let parsed = match color.trim().starts_with('#') {
+                true => ConfigColor::from_hex(color.trim()),
+                false => ConfigColor::from_name(color),
+            };
+            if parsed.is_none() {
+                errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Unknown color {:?}, expected a CSS color name or \"#rrggbb\"", color)));
+            }
+            return parsed;
+        }
+        Value::Array(components) => components,
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected a color as [R, G, B], [R, G, B, A], \"#rrggbb\" or a color name, found {}", value.type_str())));
+            return None;
+        }
+    };
+    if components.len() != 3 && components.len() != 4 {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("A color must have 3 or 4 components, found {}", components.len())));
+        return None;
+    }
+    // With any decimal component, all of them are fractions between 0 and 1
+    let fractions = components.iter().any(|component| component.is_float());
+    let mut color = Vec::with_capacity(4);
+    for (i, component) in components.iter().enumerate() {
+        let parsed = match (fractions, component) {
+            (true, Value::Float(c)) if (0.0..=1.0).contains(c) => Some((c * 255.0).round() as u8),
+            (true, Value::Integer(c @ (0 | 1))) => Some(*c as u8 * 255),
+            (false, Value::Integer(c)) => u8::try_from(*c).ok(),
+            _ => None,
+        };
+        match parsed {
+            Some(c) => color.push(c),
+            None => {
+                let expected = match fractions {
+                    true => "numbers between 0 and 1",
+                    false => "integers between 0 and 255",
+                };
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue,
+                    [path, vec![i.into()]].concat(),
+                    format!("Color components must be {}, found {}", expected, component),
+                ));
+                return None;
+            }
+        }
+    }
+    Some(ConfigColor::rgba(color[0], color[1], color[2], color.get(3).copied().unwrap_or(255)))
+}
+
+/// Names of the CSS colors (the X11 colors, with the CSS values for gray, green, maroon and purple)
+const NAMED_COLORS: [(&str, [u8; 3]); 148] = [
+    ("aliceblue", [240, 248, 255]), ("antiquewhite", [250, 235, 215]), ("aqua", [0, 255, 255]), ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]), ("beige", [245, 245, 220]), ("bisque", [255, 228, 196]), ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]), ("blue", [0, 0, 255]), ("blueviolet", [138, 43, 226]), ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]), ("cadetblue", [95, 158, 160]), ("chartreuse", [127, 255, 0]), ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]), ("cornflowerblue", [100, 149, 237]), ("cornsilk", [255, 248, 220]), ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]), ("darkblue", [0, 0, 139]), ("darkcyan", [0, 139, 139]), ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]), ("darkgreen", [0, 100, 0]), ("darkgrey", [169, 169, 169]), ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]), ("darkolivegreen", [85, 107, 47]), ("darkorange", [255, 140, 0]), ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]), ("darksalmon", [233, 150, 122]), ("darkseagreen", [143, 188, 143]), ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]), ("darkslategrey", [47, 79, 79]), ("darkturquoise", [0, 206, 209]), ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]), ("deepskyblue", [0, 191, 255]), ("dimgray", [105, 105, 105]), ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]), ("firebrick", [178, 34, 34]), ("floralwhite", [255, 250, 240]), ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]), ("gainsboro", [220, 220, 220]), ("ghostwhite", [248, 248, 255]), ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]), ("gray", [128, 128, 128]), ("green", [0, 128, 0]), ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]), ("honeydew", [240, 255, 240]), ("hotpink", [255, 105, 180]), ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]), ("ivory", [255, 255, 240]), ("khaki", [240, 230, 140]), ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]), ("lawngreen", [124, 252, 0]), ("lemonchiffon", [255, 250, 205]), ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]), ("lightcyan", [224, 255, 255]), ("lightgoldenrodyellow", [250, 250, 210]), ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]), ("lightgrey", [211, 211, 211]), ("lightpink", [255, 182, 193]), ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]), ("lightskyblue", [135, 206, 250]), ("lightslategray", [119, 136, 153]), ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]), ("lightyellow", [255, 255, 224]), ("lime", [0, 255, 0]), ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]), ("magenta", [255, 0, 255]), ("maroon", [128, 0, 0]), ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]), ("mediumorchid", [186, 85, 211]), ("mediumpurple", [147, 112, 219]), ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]), ("mediumspringgreen", [0, 250, 154]), ("mediumturquoise", [72, 209, 204]), ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]), ("mintcream", [245, 255, 250]), ("mistyrose", [255, 228, 225]), ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]), ("navy", [0, 0, 128]), ("oldlace", [253, 245, 230]), ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]), ("orange", [255, 165, 0]), ("orangered", [255, 69, 0]), ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]), ("palegreen", [152, 251, 152]), ("paleturquoise", [175, 238, 238]), ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]), ("peachpuff", [255, 218, 185]), ("peru", [205, 133, 63]), ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]), ("powderblue", [176, 224, 230]), ("purple", [128, 0, 128]), ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]), ("rosybrown", [188, 143, 143]), ("royalblue", [65, 105, 225]), ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]), ("sandybrown", [244, 164, 96]), ("seagreen", [46, 139, 87]), ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]), ("silver", [192, 192, 192]), ("skyblue", [135, 206, 235]), ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]), ("slategrey", [112, 128, 144]), ("snow", [255, 250, 250]), ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]), ("tan", [210, 180, 140]), ("teal", [0, 128, 128]), ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]), ("turquoise", [64, 224, 208]), ("violet", [238, 130, 238]), ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]), ("whitesmoke", [245, 245, 245]), ("yellow", [255, 255, 0]), ("yellowgreen", [154, 205, 50]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(toml: &str) -> (Option<ConfigColor>, Vec<ConfigError>) {
+        let value = toml::from_str::<toml::Table>(&format!("color = {}", toml)).unwrap()["color"].clone();
+        let mut errors = Vec::new();
+        (parse_color(&value, vec!["color".into()], &mut errors), errors)
+    }
+
+    #[test]
+    fn hexadecimal_colors() {
+        assert_eq!(color("\"#f80\"").0, Some(ConfigColor::rgb(255, 136, 0)));
+        assert_eq!(color("\"#f808\"").0, Some(ConfigColor::rgba(255, 136, 0, 136)));
+        assert_eq!(color("\"#FF8800\"").0, Some(ConfigColor::rgb(255, 136, 0)));
+        assert_eq!(color("\"#ff880080\"").0, Some(ConfigColor::rgba(255, 136, 0, 128)));
+        assert_eq!(ConfigColor::from_hex("ff8800"), Some(ConfigColor::rgb(255, 136, 0)));
+        assert_eq!(ConfigColor::from_hex("#ff88"), Some(ConfigColor::rgba(255, 255, 136, 136)));
+        assert_eq!(ConfigColor::from_hex("#ff880"), None);
+        assert_eq!(ConfigColor::from_hex("#gg8800"), None);
+    }
+
+    #[test]
+    fn named_colors_ignore_case_and_spaces() {
+        assert_eq!(color("\"orange\"").0, Some(ConfigColor::rgb(255, 165, 0)));
+        assert_eq!(color("\"Steel Blue\"").0, Some(ConfigColor::rgb(70, 130, 180)));
+        assert_eq!(ConfigColor::from_name("LIGHT_sea-green"), Some(ConfigColor::rgb(32, 178, 170)));
+        let (parsed, errors) = color("\"bluish\"");
+        assert_eq!(parsed, None);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn integer_and_fraction_components() {
+        assert_eq!(color("[255, 136, 0]").0, Some(ConfigColor::rgb(255, 136, 0)));
+        assert_eq!(color("[255, 136, 0, 128]").0, Some(ConfigColor::rgba(255, 136, 0, 128)));
+        assert_eq!(color("[1.0, 0.5, 0.0]").0, Some(ConfigColor::rgb(255, 128, 0)));
+        // With a decimal component, the integers 0 and 1 are fractions too
+        assert_eq!(color("[1.0, 0, 0]").0, Some(ConfigColor::rgb(255, 0, 0)));
+        assert_eq!(color("[1, 1, 0]").0, Some(ConfigColor::rgb(1, 1, 0)));
+    }
+
+    #[test]
+    fn invalid_components_are_reported() {
+        for (invalid, key_path) in [
+            ("[255, 0]", vec!["color".into()]),
+            ("[256, 0, 0]", vec!["color".into(), 0.into()]),
+            ("[0, -1, 0]", vec!["color".into(), 1.into()]),
+            ("[1.5, 0.0, 0.0]", vec!["color".into(), 0.into()]),
+            ("[1.0, 2, 0]", vec!["color".into(), 1.into()]),
+            ("true", vec!["color".into()]),
+        ] {
+            let (parsed, errors) = color(invalid);
+            assert_eq!(parsed, None, "{}", invalid);
+            assert_eq!(errors.len(), 1, "{}", invalid);
+            assert_eq!(errors[0].key_path, key_path, "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use serde::Deserialize;
+use toml::Value;
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::parse_float;
+
+/// Color of the markers or the joins of a configuration that follows a value computed every frame, instead of a fixed color.
+/// The value is mapped to the palette between the ends of the range.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ColorMap {
+    pub value: ColorMapValue,
+    pub palette: Palette,
+    /// Values mapped to the first and the last color of the palette. If None, the minimum and the maximum of the trial
+    pub range: Option<[f64; 2]>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum ColorMapValue {
+    Speed,              // Of the marker, in m/s
+    Distance(String),   // From the marker to a reference point, in metres
+    Length,             // Of the join, in metres
+    LengthChange,       // Of the join, relative to its length in the first frame where both points exist, in metres
+}
+
+impl ColorMapValue {
+    /// Markers use speed and distance, joins use length and length change
+    pub fn is_for_markers(&self) -> bool {
+        matches!(self, ColorMapValue::Speed | ColorMapValue::Distance(_))
+    }
+
+    /// Name of the value, with its unit, as shown in the legend
+    pub fn description(&self) -> String {
+        match self {
+            ColorMapValue::Speed => "Speed (m/s)".to_string(),
+            ColorMapValue::Distance(reference) => format!("Distance to {} (m)", reference),
+            ColorMapValue::Length => "Length (m)".to_string(),
+            ColorMapValue::LengthChange => "Length change (m)".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Palette {
+    #[default]
+    Viridis,
+    Plasma,
+    Turbo,
+    Coolwarm,   // Diverging, blue - white - red. Useful for changes around 0
+    Grayscale,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [Palette::Viridis, Palette::Plasma, Palette::Turbo, Palette::Coolwarm, Palette::Grayscale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Viridis => "viridis",
+            Palette::Plasma => "plasma",
+            Palette::Turbo => "turbo",
+            Palette::Coolwarm => "coolwarm",
+            Palette::Grayscale => "grayscale",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL.into_iter().find(|palette| palette.name() == name.to_lowercase())
+    }
+
+    /// Color (RGB) at a position of the palette, between 0 (first color) and 1 (last color). Values out of [0, 1] are clamped.
+    pub fn color(&self, t: f64) -> [u8; 3] {
+        let stops: &[[u8; 3]] = match self {
+            Palette::Viridis => &[[68, 1, 84], [59, 82, 139], [33, 145, 140], [94, 201, 98], [253, 231, 37]],
+            Palette::Plasma => &[[13, 8, 135], [126, 3, 168], [204, 71, 120], [248, 149, 64], [240, 249, 33]],
+            Palette::Turbo => &[[48, 18, 59], [40, 188, 235], [164, 252, 60], [251, 128, 34], [122, 4, 3]],
+            Palette::Coolwarm => &[[59, 76, 192], [221, 221, 221], [180, 4, 38]],
+            Palette::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        };
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let position = t * (stops.len() - 1) as f64;
+        let i = (position.floor() as usize).min(stops.len() - 2);
+        let fraction = position - i as f64;
+        let mut color = [0; 3];
+        for c in 0..3 {
+            color[c] = (stops[i][c] as f64 + (stops[i + 1][c] as f64 - stops[i][c] as f64) * fraction).round() as u8;
+        }
+        color
+    }
+}
+
+impl ColorMap {
+    /// Color of a value, with the range of the color map, or `auto_range` (the minimum and maximum of the trial) if it has none
+    pub fn color(&self, value: f64, auto_range: [f64; 2]) -> [u8; 3] {
+        let [min, max] = self.range.unwrap_or(auto_range);
+        let t = if max > min { (value - min) / (max - min) } else { 0.5 };
+        self.palette.color(t)
+    }
+}
+
+/// Parses a color map: a table with the `value` (speed, distance, length or length_change), the `palette` and, optionally,
+/// the `range` (`[min, max]`). A distance needs the `reference` point. `for_markers` tells if it is the color map of the
+/// markers (speed or distance) or of the joins (length or length_change).
+pub(crate) fn parse_color_map(value: &Value, for_markers: bool, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<ColorMap> {
+    let key_path = |key: &str| [path.clone(), vec![key.into()]].concat();
+    let Value::Table(table) = value else {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected a table with the value and the palette, found {}", value.type_str())));
+        return None;
+    };
+
+    let color_value = match (table.get("value"), table.get("reference")) {
+        (Some(Value::String(v)), _) if v == "speed" => ColorMapValue::Speed,
+        (Some(Value::String(v)), Some(Value::String(reference))) if v == "distance" => ColorMapValue::Distance(reference.clone()),
+        (Some(Value::String(v)), _) if v == "distance" => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("reference"), "A distance needs the reference point"));
+            return None;
+        }
+        (Some(Value::String(v)), _) if v == "length" => ColorMapValue::Length,
+        (Some(Value::String(v)), _) if v == "length_change" => ColorMapValue::LengthChange,
+        (Some(Value::String(v)), _) => {
+            errors.push(ConfigError::new(
+                ConfigErrorKind::Unsupported,
+                key_path("value"),
+                format!("Unknown value {:?}, expected speed, distance, length or length_change. Ignoring the color map", v),
+            ));
+            return None;
+        }
+        (Some(other), _) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("value"), format!("Expected a string, found {}", other.type_str())));
+            return None;
+        }
+        (None, _) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "A color map needs the value it represents"));
+            return None;
+        }
+    };
+    if color_value.is_for_markers() != for_markers {
+        let expected = if for_markers { "speed or distance" } else { "length or length_change" };
+        errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            key_path("value"),
+            format!("{} is not available here, expected {}. Ignoring the color map", color_value.description(), expected),
+        ));
+        return None;
+    }
+
+    let palette = match table.get("palette") {
+        Some(Value::String(name)) => Palette::from_name(name).unwrap_or_else(|| {
+            errors.push(ConfigError::new(
+                ConfigErrorKind::Unsupported,
+                key_path("palette"),
+                format!("Unknown palette {:?}, expected {}. Using viridis", name, Palette::ALL.map(|p| p.name()).join(", ")),
+            ));
+            Palette::default()
+        }),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("palette"), format!("Expected the name of a palette, found {}", other.type_str())));
+            Palette::default()
+        }
+        None => Palette::default(),
+    };
+
+    let range = table.get("range").and_then(|range| match range {
+        Value::Array(ends) if ends.len() == 2 => {
+            let min = parse_float(&ends[0], [key_path("range"), vec![0usize.into()]].concat(), errors)?;
+            let max = parse_float(&ends[1], [key_path("range"), vec![1usize.into()]].concat(), errors)?;
+            if min >= max {
+                errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("range"), "The minimum of the range must be lower than the maximum. Using the range of the trial"));
+                return None;
+            }
+            Some([min, max])
+        }
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, key_path("range"), "Expected the range as [min, max]"));
+            None
+        }
+    });
+
+    Some(ColorMap { value: color_value, palette, range })
+}
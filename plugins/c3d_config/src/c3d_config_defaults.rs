@@ -0,0 +1,163 @@
+use serde::Deserialize;
+use toml::{Value, map::Map};
+
+use crate::c3d_config_axes::{Axis, Handedness, LabAxes};
+use crate::c3d_config_color::{parse_color, ConfigColor};
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::{parse_float, ConfigFile};
+
+/// Values of `[defaults]`, used when neither the point groups nor the configuration set them.
+/// The fields that are None take the built-in value, returned by the getters.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Defaults {
+    /// Radius of the sphere of a marker with point_size 1, in metres. Built-in 0.014
+    pub marker_radius: Option<f64>,
+    /// Multiplier of the marker radius. Built-in 1
+    pub point_size: Option<f64>,
+    /// Built-in blue
+    pub point_color: Option<ConfigColor>,
+    /// Built-in green
+    pub join_color: Option<ConfigColor>,
+    /// Built-in 0.01
+    pub line_thickness: Option<f64>,
+    /// Colors of the 3 vectors of a point with 3 vectors (its axes). Built-in red, green and blue
+    pub vector_colors: Option<[ConfigColor; 3]>,
+    /// Color of the vectors of a point with 1, 2 or more than 3 vectors. Built-in yellow
+    pub vector_color: Option<ConfigColor>,
+    /// Factor from millimetres (the units of the C3D points once loaded, see POINT:UNITS) to metres. Built-in 0.001
+    pub unit_scale: Option<f64>,
+    /// Axis of the lab that points up. Built-in +Z
+    pub up_axis: Option<Axis>,
+    /// Axis of the lab that points forward. Built-in +X, or +Y if the up axis is X
+    pub forward_axis: Option<Axis>,
+    /// Built-in right-handed
+    pub handedness: Option<Handedness>,
+}
+
+impl Defaults {
+    pub fn get_marker_radius(&self) -> f64 {
+        self.marker_radius.unwrap_or(0.014)
+    }
+
+    pub fn get_point_size(&self) -> f64 {
+        self.point_size.unwrap_or(1.0)
+    }
+
+    pub fn get_point_color(&self) -> ConfigColor {
+        self.point_color.unwrap_or(ConfigColor::rgb(0, 0, 255))
+    }
+
+    pub fn get_join_color(&self) -> ConfigColor {
+        self.join_color.unwrap_or(ConfigColor::rgb(0, 255, 0))
+    }
+
+    pub fn get_line_thickness(&self) -> f64 {
+        self.line_thickness.unwrap_or(0.01)
+    }
+
+    pub fn get_vector_colors(&self) -> [ConfigColor; 3] {
+        self.vector_colors.unwrap_or([ConfigColor::rgb(255, 0, 0), ConfigColor::rgb(0, 255, 0), ConfigColor::rgb(0, 0, 255)])
+    }
+
+    pub fn get_vector_color(&self) -> ConfigColor {
+        self.vector_color.unwrap_or(ConfigColor::rgb(255, 220, 0))
+    }
+
+    pub fn get_unit_scale(&self) -> f64 {
+        self.unit_scale.unwrap_or(0.001)
+    }
+
+    /// Coordinate system of the lab, if any of up_axis, forward_axis or handedness is set. Otherwise, the one of the
+    /// C3D loader is used. If forward_axis is along up_axis, the default forward axis of the up axis is used.
+    pub fn get_lab_axes(&self) -> Option<LabAxes> {
+        if self.up_axis.is_none() && self.forward_axis.is_none() && self.handedness.is_none() {
+            return None;
+        }
+        let mut lab_axes = LabAxes::with_up(self.up_axis.unwrap_or(Axis::Z));
+        lab_axes.forward = self.forward_axis.unwrap_or(lab_axes.forward);
+        lab_axes.handedness = self.handedness.unwrap_or_default();
+        Some(lab_axes.or_with_up())
+    }
+}
+
+impl ConfigFile {
+    /// Values of `[defaults]`. Without the section, all of them are the built-in values.
+    pub fn get_defaults(&self) -> &Defaults {
+        &self.defaults
+    }
+
+    pub fn set_defaults(&mut self, defaults: Defaults) {
+        self.defaults = defaults;
+    }
+}
+
+/// Parses the `[defaults]` table. Invalid values are reported and take the built-in value.
+pub(crate) fn parse_defaults(table: &Map<String, Value>, errors: &mut Vec<ConfigError>) -> Defaults {
+    let path = |key: &str| vec!["defaults".into(), key.into()];
+    let positive = |value: &Value, key: &str, errors: &mut Vec<ConfigError>| {
+        let number = parse_float(value, path(key), errors)?;
+        if number <= 0.0 {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path(key), format!("Expected a positive number, found {}", number)));
+            return None;
+        }
+        Some(number)
+    };
+
+    let marker_radius = table.get("marker_radius").and_then(|v| positive(v, "marker_radius", errors));
+    let point_size = table.get("point_size").and_then(|v| positive(v, "point_size", errors));
+    let point_color = table.get("point_color").and_then(|v| parse_color(v, path("point_color"), errors));
+    let join_color = table.get("join_color").and_then(|v| parse_color(v, path("join_color"), errors));
+    let line_thickness = table.get("line_thickness").and_then(|v| positive(v, "line_thickness", errors));
+    let vector_colors = table.get("vector_colors").and_then(|value| match value {
+        Value::Array(colors) if colors.len() == 3 => {
+            let colors = colors.iter().enumerate()
+                .map(|(i, color)| parse_color(color, [path("vector_colors"), vec![i.into()]].concat(), errors))
+                .collect::<Option<Vec<_>>>()?;
+            Some([colors[0], colors[1], colors[2]])
+        }
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("vector_colors"), "Expected the 3 colors of the axes, as [x_color, y_color, z_color]"));
+            None
+        }
+    });
+    let vector_color = table.get("vector_color").and_then(|v| parse_color(v, path("vector_color"), errors));
+    let unit_scale = table.get("unit_scale").and_then(|v| positive(v, "unit_scale", errors));
+    let axis = |key: &str, errors: &mut Vec<ConfigError>| match table.get(key) {
+        Some(Value::String(name)) if Axis::from_name(name).is_some() => Axis::from_name(name),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path(key), format!("Expected an axis (\"x\", \"-y\", \"+z\"...), found {}", other)));
+            None
+        }
+        None => None,
+    };
+    let up_axis = axis("up_axis", errors);
+    let mut forward_axis = axis("forward_axis", errors);
+    if let Some(forward) = forward_axis {
+        if up_axis.unwrap_or(Axis::Z).index() == forward.index() {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("forward_axis"), "The forward axis must be different from the up axis. Ignoring it"));
+            forward_axis = None;
+        }
+    }
+    let handedness = match table.get("handedness") {
+        Some(Value::String(handedness)) if handedness.eq_ignore_ascii_case("right") => Some(Handedness::Right),
+        Some(Value::String(handedness)) if handedness.eq_ignore_ascii_case("left") => Some(Handedness::Left),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("handedness"), format!("Expected \"right\" or \"left\", found {}", other)));
+            None
+        }
+        None => None,
+    };
+    Defaults {
+        marker_radius,
+        point_size,
+        point_color,
+        join_color,
+        line_thickness,
+        vector_colors,
+        vector_color,
+        unit_scale,
+        up_axis,
+        forward_axis,
+        handedness,
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+/// Segment of the path to a value in the configuration file. For example, `config1.joins[2]` is
+/// `[Key("config1"), Key("joins"), Index(2)]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigKey {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for ConfigKey {
+    fn from(key: &str) -> Self {
+        ConfigKey::Key(key.to_string())
+    }
+}
+
+impl From<&String> for ConfigKey {
+    fn from(key: &String) -> Self {
+        ConfigKey::Key(key.clone())
+    }
+}
+
+impl From<usize> for ConfigKey {
+    fn from(index: usize) -> Self {
+        ConfigKey::Index(index)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigErrorKind {
+    /// The file could not be read
+    Io,
+    /// The file is not valid TOML (or YAML)
+    Syntax,
+    /// A value has an unexpected type, or an unexpected number of elements
+    InvalidValue,
+    /// A reference to something that is not defined in the file, as a point group
+    UnknownReference,
+    /// The value is valid, but it is not supported, as an unknown join shape. A fallback is used instead.
+    Unsupported,
+}
+
+/// Error found while reading a configuration file. It contains the path to the value that caused it and, if known,
+/// its position in the file (1-based line and column).
+///
+/// Io and Syntax errors are fatal, and are returned by parse_config. The rest are collected in the ConfigFile
+/// (see ConfigFile::get_diagnostics), and the offending value is ignored or replaced by a fallback.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub kind: ConfigErrorKind,
+    pub key_path: Vec<ConfigKey>,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl ConfigError {
+    pub fn new(kind: ConfigErrorKind, key_path: Vec<ConfigKey>, message: impl Into<String>) -> Self {
+        ConfigError {
+            kind,
+            key_path,
+            message: message.into(),
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Sets the position of the error from a byte offset in the source text
+    pub fn with_offset(mut self, source: &str, offset: usize) -> Self {
+        let (line, column) = line_column(source, offset);
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub fn with_line_column(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    /// Path to the value as written in the file, as `config1.joins[2].shape`
+    pub fn key_path_string(&self) -> String {
+        let mut path = String::new();
+        for key in &self.key_path {
+            match key {
+                ConfigKey::Key(key) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(key);
+                }
+                ConfigKey::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        matches!(self.kind, ConfigErrorKind::Io | ConfigErrorKind::Syntax)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        if !self.key_path.is_empty() {
+            write!(f, "{}: ", self.key_path_string())?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
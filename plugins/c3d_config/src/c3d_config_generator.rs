@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::c3d_config_color::ConfigColor;
+use crate::c3d_config_toml::{Config, ConfigFile, JoinShape, PointGroupConfig};
+
+/// Name of the configuration built by ConfigFile::from_labels
+pub const GENERATED_CONFIG_NAME: &str = "default";
+
+/// Body regions recognised by the prefix of the label, after the side (`L` or `R`). The head markers are exactly `FHD`
+/// and `BHD` (`LFHD`, `RBHD`).
+const REGIONS: [(&str, &str); 16] = [
+    ("FHD", "head"), ("BHD", "head"),
+    ("SHO", "arm"), ("UPA", "arm"), ("ELB", "arm"), ("FRA", "arm"), ("WR", "arm"), ("FIN", "arm"),
+    ("ASI", "pelvis"), ("PSI", "pelvis"),
+    ("THI", "leg"), ("KNE", "leg"), ("TIB", "leg"), ("ANK", "leg"), ("HEE", "leg"), ("TOE", "leg"),
+];
+
+/// Markers of the trunk and the pelvis that have no side
+const CENTRAL_MARKERS: [(&str, &str); 6] = [
+    ("C7", "trunk"), ("T10", "trunk"), ("CLAV", "trunk"), ("STRN", "trunk"), ("RBAK", "trunk"), ("SACR", "pelvis"),
+];
+
+/// Segments of the Plug-in Gait stick figure. The markers of a chain are joined to the next one in the C3D, so the wand
+/// markers (`UPA`, `FRA`, `THI`, `TIB`) can be missing. The sided chains are written without the side, and are joined on
+/// both sides.
+const CHAINS: [&[&str]; 6] = [
+    &["LFHD", "RFHD", "RBHD", "LBHD", "LFHD"],
+    &["LSHO", "C7", "RSHO"], &["LSHO", "CLAV", "RSHO"], &["C7", "T10"], &["CLAV", "STRN"],
+    &["LASI", "RASI", "RPSI", "SACR", "LPSI", "LASI"],
+];
+const SIDE_CHAINS: [&[&str]; 3] = [
+    &["SHO", "UPA", "ELB", "FRA", "WRA"], &["WRA", "WRB", "FIN", "WRA"],
+    &["ASI", "THI", "KNE", "TIB", "ANK", "HEE", "TOE", "ANK"],
+];
+
+impl ConfigFile {
+    /// Builds a starter configuration from the labels of a C3D file, for the files that have no configuration.
+    ///
+    /// The markers are grouped by body region and side (`head`, `trunk`, `pelvis`, `left_arm`, `right_leg`...) from the
+    /// Plug-in Gait names (`LKNE`, `RSHO`...), ignoring the subject prefix (`Subject:LKNE`). The other markers go to `other`.
+    /// The left side is red and the right side is green. The configuration `default` shows all the markers and joins the
+    /// segments of the Plug-in Gait stick figure whose markers are in the C3D.
+    pub fn from_labels(labels: &[String]) -> ConfigFile {
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        let mut plug_in_gait: HashMap<&str, &String> = HashMap::new();
+        for label in labels {
+            let name = label.rsplit(':').next().unwrap_or(label);
+            let group = match body_region(name) {
+                Some((Some(side), region)) => format!("{}_{}", side, region),
+                Some((None, region)) => region.to_string(),
+                None => "other".to_string(),
+            };
+            plug_in_gait.entry(name).or_insert(label);
+            match groups.iter_mut().find(|(group_name, _)| *group_name == group) {
+                Some((_, points)) if !points.contains(label) => points.push(label.clone()),
+                Some(_) => {}
+                None => groups.push((group, vec![label.clone()])),
+            }
+        }
+        groups.sort_by_key(|(group_name, _)| group_order(group_name));
+
+        let mut config_file = ConfigFile::default();
+        let mut config = Config::default();
+        for (group_name, points) in groups {
+            let color = if group_name.starts_with("left_") {
+                Some(ConfigColor::rgb(255, 0, 0))
+            } else if group_name.starts_with("right_") {
+                Some(ConfigColor::rgb(0, 200, 0))
+            } else {
+                None
+            };
+            if let Some(color) = color {
+                let mut group_config = PointGroupConfig::default();
+                group_config.point_color = Some(color);
+                group_config.join_color = Some(color);
+                config_file.add_point_group_config(group_name.clone(), group_config);
+            }
+            config.add_visible_point_group(points.clone());
+            config_file.add_point_group(group_name, points);
+        }
+
+        let side_chains = ["L", "R"].iter().flat_map(|side| SIDE_CHAINS.iter().map(move |chain| {
+            chain.iter().map(|marker| format!("{}{}", side, marker)).collect::<Vec<_>>()
+        }));
+        let chains = CHAINS.iter().map(|chain| chain.iter().map(|marker| marker.to_string()).collect::<Vec<_>>()).chain(side_chains);
+        let mut joins: Vec<(Vec<String>, JoinShape)> = Vec::new();
+        for chain in chains {
+            let present = chain.iter().filter_map(|marker| plug_in_gait.get(marker.as_str())).collect::<Vec<_>>();
+            for pair in present.windows(2) {
+                let points = vec![pair[0].to_string(), pair[1].to_string()];
+                if pair[0] != pair[1] && !joins.iter().any(|(join, _)| *join == points) {
+                    joins.push((points, JoinShape::Line));
+                }
+            }
+        }
+        if !joins.is_empty() {
+            config.joins = Some(joins);
+        }
+        config_file.config_name.insert(GENERATED_CONFIG_NAME.to_string(), config);
+        config_file
+    }
+}
+
+/// Side (`left` or `right`, if the marker has one) and body region of a Plug-in Gait label
+fn body_region(name: &str) -> Option<(Option<&'static str>, &'static str)> {
+    if let Some((_, region)) = CENTRAL_MARKERS.iter().find(|(marker, _)| *marker == name) {
+        return Some((None, region));
+    }
+    let (side, rest) = match name.split_at_checked(1)? {
+        ("L", rest) => ("left", rest),
+        ("R", rest) => ("right", rest),
+        _ => return None,
+    };
+    let (_, region) = REGIONS.iter().find(|(prefix, region)| match *region {
+        "head" => rest == *prefix,
+        _ => rest.starts_with(prefix),
+    })?;
+    match *region {
+        "head" => Some((None, "head")),
+        _ => Some((Some(side), region)),
+    }
+}
+
+/// Groups from head to feet, left before right, and `other` at the end
+fn group_order(group_name: &str) -> usize {
+    ["head", "trunk", "left_arm", "right_arm", "pelvis", "left_pelvis", "right_pelvis", "left_leg", "right_leg"]
+        .iter()
+        .position(|name| *name == group_name)
+        .unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn markers_are_grouped_by_region_and_side() {
+        let config_file = ConfigFile::from_labels(&labels(&["Sub:LFHD", "Sub:RBHD", "C7", "LELB", "RKNE", "SACR", "LASI", "LXHD", "EXTRA"]));
+        let groups = config_file.get_point_groups().unwrap();
+
+        assert_eq!(groups["head"], labels(&["Sub:LFHD", "Sub:RBHD"]));
+        assert_eq!(groups["trunk"], labels(&["C7"]));
+        assert_eq!(groups["left_arm"], labels(&["LELB"]));
+        assert_eq!(groups["right_leg"], labels(&["RKNE"]));
+        assert_eq!(groups["pelvis"], labels(&["SACR"]));
+        assert_eq!(groups["left_pelvis"], labels(&["LASI"]));
+        assert_eq!(groups["other"], labels(&["LXHD", "EXTRA"]));
+        assert_eq!(config_file.get_point_color("LELB", GENERATED_CONFIG_NAME), Some(ConfigColor::rgb(255, 0, 0)));
+        assert_eq!(config_file.get_point_color("RKNE", GENERATED_CONFIG_NAME), Some(ConfigColor::rgb(0, 200, 0)));
+        assert_eq!(config_file.get_point_color("C7", GENERATED_CONFIG_NAME), None);
+    }
+
+    #[test]
+    fn present_markers_of_the_chains_are_joined() {
+        let config_file = ConfigFile::from_labels(&labels(&["LSHO", "LELB", "LWRA", "C7", "RSHO", "RWRA"]));
+        let joins = config_file.get_config(GENERATED_CONFIG_NAME).unwrap().get_joins().unwrap();
+        let points = joins.iter().map(|(points, _)| points.clone()).collect::<Vec<_>>();
+
+        // The missing markers are skipped: LSHO is joined to RSHO without CLAV, and RSHO to RWRA without RELB
+        assert_eq!(points, vec![
+            labels(&["LSHO", "C7"]), labels(&["C7", "RSHO"]), labels(&["LSHO", "RSHO"]), labels(&["LSHO", "LELB"]),
+            labels(&["LELB", "LWRA"]), labels(&["RSHO", "RWRA"]),
+        ]);
+    }
+}
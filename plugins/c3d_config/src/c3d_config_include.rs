@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::{AssetPath, LoadContext};
+use bevy::tasks::block_on;
+use bevy::utils::BoxedFuture;
+use toml::Value;
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_mkr::parse_mkr;
+use crate::c3d_config_toml::toml_config_table;
+use crate::c3d_config_yaml::yaml_config_table;
+
+/// Sections that are merged key by key with the ones of the included files: a point group, alias, virtual point, view or
+/// default of the including file replaces the one with the same name. The rest of the tables (configurations and
+/// `[group.config]`) replace the whole table with the same name.
+const MERGED_SECTIONS: [&str; 5] = ["point_groups", "aliases", "virtual_points", "views", "defaults"];
+
+/// Top level table of the content of a configuration file, in the layout of the TOML format, with the format of its
+/// extension. The second value has the problems of the translation (for YAML files).
+pub(crate) fn config_table(content: &str, path: &Path) -> Result<(HashMap<String, Value>, Vec<ConfigError>), ConfigError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml") | Some("yml") => yaml_config_table(content),
+        Some("mkr") => {
+            let config_file = parse_mkr(content, false)?.to_config_file();
+            Ok((toml_config_table(&config_file.to_toml_string())?, Vec::new()))
+        }
+        _ => Ok((toml_config_table(content)?, Vec::new())),
+    }
+}
+
+/// Removes the `include` key of a configuration, and returns the files it includes, in order.
+/// It can be a file or an array of files.
+pub(crate) fn take_includes(config: &mut HashMap<String, Value>, errors: &mut Vec<ConfigError>) -> Vec<String> {
+    match config.remove("include") {
+        None => Vec::new(),
+        Some(Value::String(file)) => vec![file],
+        Some(Value::Array(files)) => files.into_iter().enumerate().filter_map(|(i, file)| match file {
+            Value::String(file) => Some(file),
+            other => {
+                errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, vec!["include".into(), i.into()], format!("Expected the path of a file, found {}", other.type_str())));
+                None
+            }
+        }).collect(),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, vec!["include".into()], format!("Expected a file or an array of files, found {}", other.type_str())));
+            Vec::new()
+        }
+    }
+}
+
+/// Merges a configuration on top of the included ones (see MERGED_SECTIONS). Included point groups keep their
+/// position, so the groups of the included files go before the new ones of the including file.
+pub(crate) fn merge_config_tables(mut base: HashMap<String, Value>, config: HashMap<String, Value>) -> HashMap<String, Value> {
+    for (key, value) in config {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_section)), Value::Table(section)) if MERGED_SECTIONS.contains(&key.as_str()) => base_section.extend(section),
+            (_, value) => { base.insert(key, value); }
+        }
+    }
+    base
+}
+
+/// Error of an included file, reported in its entry of `include` of the including file
+pub(crate) fn include_error(index: usize, message: impl Into<String>) -> ConfigError {
+    ConfigError::new(ConfigErrorKind::UnknownReference, vec!["include".into(), index.into()], message)
+}
+
+/// Problems found in an included file, reported in its entry of `include`, with the name of the file and their key path
+/// in the message (their position would be in the other file).
+pub(crate) fn included_diagnostics(index: usize, include: &str, diagnostics: Vec<ConfigError>) -> impl Iterator<Item = ConfigError> + '_ {
+    diagnostics.into_iter().map(move |diagnostic| {
+        let message = match diagnostic.key_path.is_empty() {
+            true => format!("In {}: {}", include, diagnostic.message),
+            false => format!("In {}, {}: {}", include, diagnostic.key_path_string(), diagnostic.message),
+        };
+        ConfigError::new(diagnostic.kind, vec!["include".into(), index.into()], message)
+    })
+}
+
+/// Where the included files are read from: the filesystem for parse_config (FileIncludes), and the asset system for the
+/// asset loader (AssetIncludes). Both resolve the includes with include_files_with.
+pub(crate) trait IncludeReader: Send {
+    /// Path of a file. Two equal paths are the same file, to detect include cycles
+    type Path: Clone + PartialEq + Send + Sync;
+
+    /// Path of the file `include`, relative to the file `parent` that includes it
+    fn resolve(&self, parent: &Self::Path, include: &str) -> Result<Self::Path, String>;
+
+    /// Path in the file system, to choose the format by the extension (see config_table)
+    fn file_path(path: &Self::Path) -> &Path;
+
+    fn read<'a>(&'a mut self, path: &'a Self::Path) -> BoxedFuture<'a, Result<Vec<u8>, String>>;
+}
+
+/// Included files read from disk. The paths are canonical, so `a/../b.toml` and `b.toml` are the same file.
+pub(crate) struct FileIncludes;
+
+impl IncludeReader for FileIncludes {
+    type Path = PathBuf;
+
+    fn resolve(&self, parent: &PathBuf, include: &str) -> Result<PathBuf, String> {
+        Ok(canonical_path(&parent.parent().unwrap_or(Path::new("")).join(include)))
+    }
+
+    fn file_path(path: &PathBuf) -> &Path {
+        path
+    }
+
+    fn read<'a>(&'a mut self, path: &'a PathBuf) -> BoxedFuture<'a, Result<Vec<u8>, String>> {
+        Box::pin(async move { fs::read(path).map_err(|e| e.to_string()) })
+    }
+}
+
+/// Included files read through the asset system. They are loader dependencies of the configuration, so it is reloaded
+/// when one of them changes.
+pub(crate) struct AssetIncludes<'c, 'l> {
+    pub(crate) load_context: &'c mut LoadContext<'l>,
+}
+
+impl IncludeReader for AssetIncludes<'_, '_> {
+    type Path = AssetPath<'static>;
+
+    fn resolve(&self, parent: &AssetPath<'static>, include: &str) -> Result<AssetPath<'static>, String> {
+        parent.resolve_embed(include).map_err(|e| format!("Invalid path {}: {}", include, e))
+    }
+
+    fn file_path<'p>(path: &'p AssetPath<'static>) -> &'p Path {
+        path.path()
+    }
+
+    fn read<'a>(&'a mut self, path: &'a AssetPath<'static>) -> BoxedFuture<'a, Result<Vec<u8>, String>> {
+        Box::pin(async move { self.load_context.read_asset_bytes(path.clone()).await.map_err(|e| e.to_string()) })
+    }
+}
+
+/// Resolves the includes of a configuration read from disk, recursively (see include_files_with). The included files are
+/// relative to the directory of the including file (`path`), or to the working directory if the configuration is not a file.
+pub(crate) fn include_files(config: HashMap<String, Value>, path: &Path, errors: &mut Vec<ConfigError>) -> HashMap<String, Value> {
+    let path = canonical_path(path);
+    // Files are read synchronously, so the future is ready when it is polled
+    block_on(include_files_with(config, path.clone(), &mut vec![path], &mut FileIncludes, errors))
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Resolves the includes of a configuration, recursively, reading the included files with `reader`. `path` is the path of
+/// the configuration, and `visited` the files that are being included, to detect include cycles.
+/// Files that cannot be read or parsed, and include cycles, are reported and ignored.
+pub(crate) fn include_files_with<'a, R: IncludeReader>(
+    mut config: HashMap<String, Value>,
+    path: R::Path,
+    visited: &'a mut Vec<R::Path>,
+    reader: &'a mut R,
+    errors: &'a mut Vec<ConfigError>,
+) -> BoxedFuture<'a, HashMap<String, Value>> {
+    Box::pin(async move {
+        let mut merged = HashMap::new();
+        for (i, include) in take_includes(&mut config, errors).iter().enumerate() {
+            let include_path = match reader.resolve(&path, include) {
+                Ok(include_path) => include_path,
+                Err(error) => {
+                    errors.push(include_error(i, error));
+                    continue;
+                }
+            };
+            if visited.contains(&include_path) {
+                errors.push(include_error(i, format!("Include cycle: {} is already being included. Ignoring it", include)));
+                continue;
+            }
+            let table = match reader.read(&include_path).await {
+                Ok(bytes) => config_table(&String::from_utf8_lossy(&bytes), R::file_path(&include_path)),
+                Err(error) => Err(ConfigError::new(ConfigErrorKind::Io, Vec::new(), error)),
+            };
+            match table {
+                Ok((table, mut table_errors)) => {
+                    visited.push(include_path.clone());
+                    let table = include_files_with(table, include_path, visited, reader, &mut table_errors).await;
+                    visited.pop();
+                    errors.extend(included_diagnostics(i, include, table_errors));
+                    merged = merge_config_tables(merged, table);
+                }
+                Err(error) => errors.push(include_error(i, format!("Could not include {}: {}", include, error.message))),
+            }
+        }
+        merge_config_tables(merged, config)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> HashMap<String, Value> {
+        toml_config_table(content).unwrap()
+    }
+
+    /// Writes the files in a new directory of the temporary directory, and returns its path
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("c3d_config_include_{}_{}", name, std::process::id()));
+        for (file, content) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn merged_sections_are_merged_key_by_key() {
+        let base = table("[point_groups]\narm = [\"ELB\"]\nleg = [\"KNE\"]\n[aliases]\nKnee = \"KNE\"\n[defaults]\npoint_size = 2\nunit_scale = 0.01\n");
+        let config = table("[point_groups]\nhead = [\"HEAD\"]\narm = [\"ELB\", \"WRI\"]\n[aliases]\nHip = \"HIP\"\n[defaults]\npoint_size = 3\n");
+        let merged = merge_config_tables(base, config);
+
+        // The group of the including file replaces the included one, and keeps its position
+        let groups = merged["point_groups"].as_table().unwrap();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["arm", "leg", "head"]);
+        assert_eq!(groups["arm"], Value::Array(vec!["ELB".into(), "WRI".into()]));
+        assert_eq!(merged["aliases"].as_table().unwrap().len(), 2);
+        let defaults = merged["defaults"].as_table().unwrap();
+        assert_eq!(defaults["point_size"], Value::Integer(3));
+        assert_eq!(defaults["unit_scale"], Value::Float(0.01));
+    }
+
+    #[test]
+    fn configurations_are_replaced_whole() {
+        let base = table("[arms]\nvisible_points = [\"ELB\"]\npoint_size = 2\n[arm.config]\npoint_color = \"red\"\nline_thickness = 2\n");
+        let config = table("[arms]\nvisible_points = [\"WRI\"]\n[arm.config]\npoint_color = \"blue\"\n");
+        let merged = merge_config_tables(base, config);
+
+        assert_eq!(merged["arms"], Value::Table(table("visible_points = [\"WRI\"]").into_iter().collect()));
+        assert_eq!(merged["arm"], Value::Table(table("[config]\npoint_color = \"blue\"").into_iter().collect()));
+    }
+
+    #[test]
+    fn includes_are_relative_to_the_including_file() {
+        let dir = write_files("relative", &[
+            ("main.toml", "include = [\"parts/groups.toml\"]\n[point_groups]\nhead = [\"HEAD\"]\n"),
+            ("parts/groups.toml", "include = \"styles.toml\"\n[point_groups]\narm = [\"ELB\"]\n"),
+            ("parts/styles.toml", "[defaults]\npoint_size = 2\n"),
+        ]);
+        let mut errors = Vec::new();
+        let config = include_files(table(&fs::read_to_string(dir.join("main.toml")).unwrap()), &dir.join("main.toml"), &mut errors);
+
+        assert_eq!(errors, Vec::new());
+        assert!(!config.contains_key("include"));
+        assert_eq!(config["point_groups"].as_table().unwrap().keys().collect::<Vec<_>>(), vec!["arm", "head"]);
+        assert_eq!(config["defaults"].as_table().unwrap()["point_size"], Value::Integer(2));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn include_cycles_and_missing_files_are_reported() {
+        let dir = write_files("cycle", &[
+            ("main.toml", "include = [\"sub/other.toml\", \"missing.toml\"]\n"),
+            ("sub/other.toml", "include = \"../main.toml\"\n[point_groups]\narm = [\"ELB\"]\n"),
+        ]);
+        let mut errors = Vec::new();
+        let config = include_files(table(&fs::read_to_string(dir.join("main.toml")).unwrap()), &dir.join("main.toml"), &mut errors);
+
+        assert!(config.contains_key("point_groups"));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].key_path, vec!["include".into(), 0.into()]);
+        assert!(errors[0].message.contains("Include cycle"), "{}", errors[0].message);
+        assert_eq!(errors[1].key_path, vec!["include".into(), 1.into()]);
+        assert!(errors[1].message.starts_with("Could not include missing.toml"), "{}", errors[1].message);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+
+use bevy::prelude::Resource;
+use calamine::{Reader, Xlsx};
+
+use crate::c3d_config_error::*;
+
+/// Names of the columns of a label table, in lowercase. The first row of the table must have these names.
+const LABEL_COLUMNS: [&str; 4] = ["label", "etiqueta", "variable c3d", "variable"];
+const DESCRIPTION_COLUMNS: [&str; 3] = ["description", "descripcion", "descripción"];
+const DISPLAY_NAME_COLUMNS: [&str; 4] = ["display_name", "display name", "alias", "nombre"];
+const UNIT_COLUMNS: [&str; 3] = ["unit", "units", "unidad"];
+
+/// Information of a C3D label, read from a label table
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LabelInfo {
+    pub label: String,
+    pub description: Option<String>,
+    /// Name shown in the GUI instead of the label
+    pub display_name: Option<String>,
+    pub unit: Option<String>,
+}
+
+/// Table of C3D labels with their description, display name and unit, imported from a CSV or XLSX file.
+/// The GUI uses it to show readable names instead of the bare labels.
+#[derive(Resource, Debug, Clone, PartialEq, Default)]
+pub struct LabelCatalog {
+    labels: HashMap<String, LabelInfo>,
+}
+
+impl LabelCatalog {
+    /// Reads a label table from a file. The format is chosen by the extension (`.csv` or `.xlsx`).
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let bytes = fs::read(path).map_err(|e|
+            ConfigError::new(ConfigErrorKind::Io, Vec::new(), format!("Could not read {}: {}", path, e))
+        )?;
+        if path.to_lowercase().ends_with(".xlsx") {
+            Self::from_xlsx(bytes)
+        } else {
+            Self::from_csv(&bytes)
+        }
+    }
+
+    /// Reads a label table from the content of a CSV file. The separator can be `,` or `;`.
+    pub fn from_csv(content: &[u8]) -> Result<Self, ConfigError> {
+        let first_line = content.split(|b| *b == b'\n').next().unwrap_or_default();
+        let delimiter = if first_line.contains(&b';') && !first_line.contains(&b',') { b';' } else { b',' };
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(content);
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| {
+                let error = ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), e.to_string());
+                match e.position() {
+                    Some(position) => error.with_line_column(position.line() as usize, 1),
+                    None => error,
+                }
+            })?;
+            rows.push(record.iter().map(str::to_string).collect());
+        }
+        Self::from_rows(rows)
+    }
+
+    /// Reads a label table from the content of a XLSX file. Only the first sheet is read.
+    pub fn from_xlsx(content: Vec<u8>) -> Result<Self, ConfigError> {
+        let syntax_error = |e: calamine::XlsxError| ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), e.to_string());
+        let mut workbook = Xlsx::new(Cursor::new(content)).map_err(syntax_error)?;
+        let range = workbook
+            .worksheet_range_at(0)
+            .ok_or_else(|| ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), "The XLSX file has no sheets"))?
+            .map_err(syntax_error)?;
+        Self::from_rows(range.rows().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect())
+    }
+
+    /// Builds the catalog from the rows of a table. The first row has the names of the columns, and a label column is required.
+    /// Rows without label are skipped.
+    fn from_rows(rows: Vec<Vec<String>>) -> Result<Self, ConfigError> {
+        let mut rows = rows.into_iter();
+        let header = rows.next().unwrap_or_default();
+        let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.trim().to_lowercase().as_str()));
+        let Some(label_column) = column(&LABEL_COLUMNS) else {
+            return Err(ConfigError::new(
+                ConfigErrorKind::InvalidValue,
+                Vec::new(),
+                format!("The label table needs a column with the labels, named one of {:?}", LABEL_COLUMNS),
+            ).with_line_column(1, 1));
+        };
+        let description_column = column(&DESCRIPTION_COLUMNS);
+        let display_name_column = column(&DISPLAY_NAME_COLUMNS);
+        let unit_column = column(&UNIT_COLUMNS);
+
+        let mut catalog = LabelCatalog::default();
+        for row in rows {
+            let cell = |column: Option<usize>| column
+                .and_then(|c| row.get(c))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+            let Some(label) = cell(Some(label_column)) else {
+                continue;
+            };
+            catalog.add_label(LabelInfo {
+                label,
+                description: cell(description_column),
+                display_name: cell(display_name_column),
+                unit: cell(unit_column),
+            });
+        }
+        Ok(catalog)
+    }
+
+    /// Adds a label to the catalog. If the label already exists, it is replaced.
+    pub fn add_label(&mut self, info: LabelInfo) {
+        self.labels.insert(info.label.clone(), info);
+    }
+
+    pub fn get(&self, label: &str) -> Option<&LabelInfo> {
+        self.labels.get(label)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn get_description(&self, label: &str) -> Option<&str> {
+        self.get(label).and_then(|info| info.description.as_deref())
+    }
+
+    /// Name to show for a label: its display name if it has one, or the label itself
+    pub fn display_name(&self, label: &str) -> String {
+        self.get(label).and_then(|info| info.display_name.clone()).unwrap_or_else(|| label.to_string())
+    }
+
+    /// Display name followed by the description, as `LUPA2 - Left upper arm superior`. Used as title of windows and graphs.
+    pub fn title(&self, label: &str) -> String {
+        match self.get_description(label) {
+            Some(description) => format!("{} - {}", self.display_name(label), description),
+            None => self.display_name(label),
+        }
+    }
+
+    /// Text with all the information of a label (label, description and unit), for tooltips
+    pub fn tooltip(&self, label: &str) -> String {
+        let mut text = label.to_string();
+        if let Some(info) = self.get(label) {
+            if let Some(description) = &info.description {
+                text.push_str(&format!("\n{}", description));
+            }
+            if let Some(unit) = &info.unit {
+                text.push_str(&format!("\nUnit: {}", unit));
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use toml::{Value, map::Map};
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::ConfigFile;
+
+/// How a configuration is presented in the interface. These fields do not change how the C3D is represented, and they are
+/// not inherited with `extends`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ConfigMetadata {
+    /// Name shown in the interface instead of the name of the table
+    pub title: Option<String>,
+    /// Shown as the tooltip of the configuration
+    pub description: Option<String>,
+    /// Position in the list of configurations. The ones with order go first, from the lowest, and then the rest by name
+    pub order: Option<i64>,
+    /// Digit key (1 to 9) that selects the configuration
+    pub hotkey: Option<u8>,
+}
+
+impl ConfigFile {
+    /// Name of a configuration to show in the interface: its title, or the name of its table if it has none
+    pub fn get_config_title(&self, config_name: &str) -> String {
+        self.get_config(config_name)
+            .and_then(|config| config.get_metadata().title.clone())
+            .unwrap_or_else(|| config_name.to_string())
+    }
+
+    pub fn get_config_description(&self, config_name: &str) -> Option<&String> {
+        self.get_config(config_name).and_then(|config| config.get_metadata().description.as_ref())
+    }
+
+    /// Configuration selected by a digit key (1 to 9): the one with that hotkey. The digits that are not the hotkey of any
+    /// configuration go, in order, to the configurations without hotkey (by get_all_config_names), and the last of these
+    /// digits selects the last of them.
+    pub fn get_config_for_hotkey(&self, digit: u8) -> Option<String> {
+        let names = self.get_all_config_names();
+        let hotkey = |name: &String| self.get_config(name).and_then(|config| config.get_metadata().hotkey);
+        if let Some(name) = names.iter().find(|name| hotkey(name) == Some(digit)) {
+            return Some(name.clone());
+        }
+        let hotkeys = names.iter().filter_map(hotkey).collect::<Vec<_>>();
+        let free_digits = (1..=9).filter(|digit| !hotkeys.contains(digit)).collect::<Vec<_>>();
+        let without_hotkey = names.iter().filter(|name| hotkey(name).is_none()).collect::<Vec<_>>();
+        match free_digits.iter().position(|free_digit| *free_digit == digit)? {
+            position if position + 1 == free_digits.len() => without_hotkey.last().map(|name| name.to_string()),
+            position => without_hotkey.get(position).map(|name| name.to_string()),
+        }
+    }
+
+    /// Reports the hotkeys used by more than one configuration. The first configuration (by get_all_config_names) keeps
+    /// the hotkey, and the rest lose it.
+    pub(crate) fn check_hotkeys(&mut self, errors: &mut Vec<ConfigError>) {
+        let mut used: HashMap<u8, String> = HashMap::new();
+        for name in self.get_all_config_names() {
+            let Some(config) = self.config_name.get_mut(&name) else {
+                continue;
+            };
+            let Some(hotkey) = config.metadata.hotkey else {
+                continue;
+            };
+            match used.get(&hotkey) {
+                Some(other) => {
+                    errors.push(ConfigError::new(
+                        ConfigErrorKind::InvalidValue,
+                        vec![(&name).into(), "hotkey".into()],
+                        format!("Hotkey {} is already used by {}. Ignoring it", hotkey, other),
+                    ));
+                    config.metadata.hotkey = None;
+                }
+                None => { used.insert(hotkey, name); }
+            }
+        }
+    }
+}
+
+/// Parses the title, description, order and hotkey of a configuration. Invalid values are reported and ignored.
+pub(crate) fn parse_metadata(table: &Map<String, Value>, name: &str, errors: &mut Vec<ConfigError>) -> ConfigMetadata {
+    let path = |key: &str| vec![name.into(), key.into()];
+    let text = |key: &str, errors: &mut Vec<ConfigError>| match table.get(key) {
+        Some(Value::String(text)) => Some(text.clone()),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path(key), format!("Expected a text, found {}", other.type_str())));
+            None
+        }
+        None => None,
+    };
+
+    let mut metadata = ConfigMetadata {
+        title: text("title", errors),
+        description: text("description", errors),
+        ..Default::default()
+    };
+    metadata.order = match table.get("order") {
+        Some(Value::Integer(order)) => Some(*order),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("order"), format!("Expected an integer, found {}", other.type_str())));
+            None
+        }
+        None => None,
+    };
+    metadata.hotkey = match table.get("hotkey") {
+        Some(Value::Integer(hotkey @ 1..=9)) => Some(*hotkey as u8),
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("hotkey"), format!("Expected a digit key from 1 to 9, found {}", other)));
+            None
+        }
+        None => None,
+    };
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::c3d_config_toml::parse_config;
+
+    #[test]
+    fn free_digits_select_the_configurations_without_hotkey() {
+        let config_file = parse_config("[a]\norder = 1\n[b]\norder = 2\nhotkey = 1\n[c]\norder = 3\n", false).unwrap();
+
+        assert_eq!(config_file.get_config_for_hotkey(1), Some("b".to_string()));
+        assert_eq!(config_file.get_config_for_hotkey(2), Some("a".to_string()));
+        assert_eq!(config_file.get_config_for_hotkey(3), Some("c".to_string()));
+        assert_eq!(config_file.get_config_for_hotkey(4), None);
+        assert_eq!(config_file.get_config_for_hotkey(9), Some("c".to_string()));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::{Config, ConfigFile, JoinShape};
+
+/// Section of a MKR file, as `[Autolabel]`. Its markers are imported as a point group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerGroup {
+    pub name: String,
+    /// Labels of the markers of the section, in the order of the file, without repetitions
+    pub markers: Vec<String>,
+    /// Lines between markers (`LFHD,RFHD,LBHD`), that Vicon uses to draw the avatar
+    pub links: Vec<Vec<String>>,
+}
+
+/// Marker set read from a Vicon MKR file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MarkerSet {
+    /// Sections of the file, in the order of the file
+    pub groups: Vec<MarkerGroup>,
+    /// Description of each label, when the file has one after the label (`LFHD Left front head`)
+    pub descriptions: HashMap<String, String>,
+}
+
+impl MarkerSet {
+    pub fn get_group(&self, name: &str) -> Option<&MarkerGroup> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn get_description(&self, label: &str) -> Option<&String> {
+        self.descriptions.get(label)
+    }
+
+    /// Builds a starter configuration from the marker set. Each section is a point group, and a configuration with the
+    /// same name shows the group and joins its links. The descriptions of the markers of the section are the description
+    /// of the configuration, one marker per line (`LFHD: Left front head`).
+    pub fn to_config_file(&self) -> ConfigFile {
+        let mut config_file = ConfigFile::default();
+        for group in &self.groups {
+            config_file.add_point_group(group.name.clone(), group.markers.clone());
+
+            let mut config = Config::default();
+            config.add_visible_point_group(group.markers.clone());
+            if !group.links.is_empty() {
+                config.joins = Some(group.links.iter().map(|link| (link.clone(), JoinShape::Line)).collect());
+            }
+            let descriptions = group.markers.iter()
+                .filter_map(|marker| self.get_description(marker).map(|description| format!("{}: {}", marker, description)))
+                .collect::<Vec<_>>();
+            if !descriptions.is_empty() {
+                config.metadata.description = Some(descriptions.join("\n"));
+            }
+            config_file.config_name.insert(group.name.clone(), config);
+        }
+        config_file
+    }
+}
+
+/// Parses a Vicon MKR file, from a file or from its content.
+///
+/// The file starts with `!MKR`, followed by sections (`[Autolabel]`). Each line of a section is a marker, with its label and,
+/// optionally, a description separated by whitespace, or a link between markers (labels separated by commas).
+/// Comments (`{* ... *}`) and the lines that start with `%` or `$` (references to other markers) are ignored.
+pub fn parse_mkr(file_or_string: &str, from_file: bool) -> Result<MarkerSet, ConfigError> {
+    let content = if from_file {
+        let bytes = fs::read(file_or_string).map_err(|e|
+            ConfigError::new(ConfigErrorKind::Io, Vec::new(), format!("Could not read {}: {}", file_or_string, e))
+        )?;
+        String::from_utf8_lossy(&bytes).to_string()
+    } else {
+        file_or_string.to_string()
+    };
+
+    let mut lines = content.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim_start_matches('\u{feff}').trim().starts_with("!MKR") => {}
+        _ => return Err(ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), "A MKR file must start with !MKR").with_line_column(1, 1)),
+    }
+
+    let mut marker_set = MarkerSet::default();
+    for (i, line) in lines {
+        let line = strip_comments(line);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') || line.starts_with('$') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            marker_set.groups.push(MarkerGroup { name: section.trim().to_string(), markers: Vec::new(), links: Vec::new() });
+            continue;
+        }
+        let Some(group) = marker_set.groups.last_mut() else {
+            return Err(ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), "Marker outside of a section").with_line_column(i + 1, 1));
+        };
+        if line.contains(',') {
+            let link = line.split(',').map(str::trim).filter(|label| !label.is_empty()).map(str::to_string).collect::<Vec<_>>();
+            if link.len() >= 2 {
+                group.links.push(link);
+            }
+            continue;
+        }
+        let (label, description) = match line.split_once(char::is_whitespace) {
+            Some((label, description)) => (label, description.trim()),
+            None => (line, ""),
+        };
+        if !group.markers.iter().any(|marker| marker == label) {
+            group.markers.push(label.to_string());
+        }
+        if !description.is_empty() {
+            marker_set.descriptions.entry(label.to_string()).or_insert_with(|| description.to_string());
+        }
+    }
+    Ok(marker_set)
+}
+
+/// Removes the comments (`{* ... *}`) of a line. A comment that is not closed lasts until the end of the line.
+fn strip_comments(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{*") {
+        result.push_str(&rest[..start]);
+        rest = match rest[start..].find("*}") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_marker_set() -> MarkerSet {
+        parse_mkr(&String::from_utf8_lossy(include_bytes!("../../../assets/GOLFPGA22.mkr")), false).unwrap()
+    }
+
+    #[test]
+    fn example_marker_set_groups() {
+        let marker_set = example_marker_set();
+
+        assert_eq!(marker_set.groups.len(), 21);
+        let autolabel = marker_set.get_group("Autolabel").unwrap();
+        assert_eq!(autolabel.markers[..4], ["LFHD", "RFHD", "LBHD", "RBHD"]);
+        assert_eq!(autolabel.markers.len(), 108);
+        assert_eq!(autolabel.links[0], ["LFHD", "RFHD", "LBHD", "RBHD"]);
+        // References to other markers are not markers of the section
+        assert!(!autolabel.markers.iter().any(|marker| marker.starts_with('%')));
+        assert_eq!(marker_set.get_group("X Factor").unwrap().markers, ["Xfactor"]);
+        assert_eq!(marker_set.get_description("STRN"), Some(&"Sternum".to_string()));
+        assert_eq!(marker_set.get_description("LSHO3"), Some(&"LEFT shoulder MIDDLE".to_string()));
+        assert_eq!(marker_set.get_description("OBJA"), None);
+    }
+
+    #[test]
+    fn example_config_file() {
+        let marker_set = example_marker_set();
+        let config_file = marker_set.to_config_file();
+
+        assert_eq!(config_file.get_point_groups_order().len(), 21);
+        let autolabel = config_file.get_config("Autolabel").unwrap();
+        assert_eq!(autolabel.get_visible_points().unwrap().len(), 108);
+        assert_eq!(autolabel.get_joins().unwrap().len(), 56);
+        let description = autolabel.get_metadata().description.as_ref().unwrap();
+        assert!(description.starts_with("LFHD: Left front head\nRFHD: Right front head\n"), "{}", description);
+        assert_eq!(config_file.get_config("lINEAR VELOCITIES").unwrap().get_joins(), None);
+    }
+
+    #[test]
+    fn comments_and_invalid_files() {
+        let marker_set = parse_mkr("!MKR#2\n[Head] {* front *}\n\tLFHD\tLeft {* not closed\n\tRFHD\n\tLFHD\tAgain\n%LFHD\nLFHD,RFHD\n", false).unwrap();
+        let group = marker_set.get_group("Head").unwrap();
+
+        assert_eq!(group.markers, ["LFHD", "RFHD"]);
+        assert_eq!(group.links, [["LFHD", "RFHD"]]);
+        assert_eq!(marker_set.get_description("LFHD"), Some(&"Left".to_string()));
+        assert_eq!(parse_mkr("[Head]\nLFHD\n", false).unwrap_err().kind, ConfigErrorKind::Syntax);
+        assert_eq!(parse_mkr("!MKR\nLFHD\n", false).unwrap_err().line, Some(2));
+    }
+}
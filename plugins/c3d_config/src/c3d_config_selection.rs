@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use toml::Value;
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::{parse_float, ConfigFile, PointMatcher};
+
+impl ConfigFile {
+    /// How well a configuration fits the labels of a C3D file: the fraction of its visible_points that match at least one
+    /// label (by the label or its canonical name), between 0 and 1. A configuration without visible_points scores 1 if it
+    /// has required_labels (and they are all in the C3D), and 0 otherwise.
+    ///
+    /// None if the configuration does not exist, a required label is missing, or the score is below its match_threshold.
+    pub fn match_score(&self, config_name: &str, labels: &[String]) -> Option<f64> {
+        let config = self.get_config(config_name)?;
+        let names = self.names_in_c3d(labels);
+
+        if let Some(required_labels) = config.get_required_labels() {
+            let matcher = PointMatcher::new(required_labels);
+            let found = names.iter().flat_map(|name| matcher.matching_points(name)).collect::<HashSet<_>>();
+            if required_labels.iter().any(|label| !found.contains(label)) {
+                return None;
+            }
+        }
+
+        let score = match config.get_visible_points().filter(|points| !points.is_empty()) {
+            Some(points) => {
+                let found = names.iter().flat_map(|name| config.get_all_points_that_match(name)).collect::<HashSet<_>>();
+                points.iter().filter(|point| found.contains(*point)).count() as f64 / points.len() as f64
+            }
+            None if config.get_required_labels().is_some_and(|labels| !labels.is_empty()) => 1.0,
+            None => 0.0,
+        };
+        match config.get_match_threshold() {
+            Some(threshold) if score < threshold => None,
+            _ => Some(score),
+        }
+    }
+
+    /// Configuration that fits best the labels of a C3D file (see match_score), with its score. With the same score, the
+    /// first in get_all_config_names order. None if no configuration fits, or none has any of its points in the C3D.
+    pub fn best_config_for_labels(&self, labels: &[String]) -> Option<(String, f64)> {
+        let mut best: Option<(String, f64)> = None;
+        for config_name in self.get_all_config_names() {
+            if let Some(score) = self.match_score(&config_name, labels) {
+                if score > 0.0 && best.as_ref().is_none_or(|(_, best_score)| score > *best_score) {
+                    best = Some((config_name, score));
+                }
+            }
+        }
+        best
+    }
+
+    /// Names a configuration can use for the points of a C3D: the labels, their canonical names, and the virtual points
+    /// whose points are all in the C3D
+    fn names_in_c3d(&self, labels: &[String]) -> Vec<String> {
+        let mut names = Vec::new();
+        for label in labels {
+            names.extend(self.label_names(label).into_iter().map(|name| name.to_string()));
+        }
+        for virtual_point in self.get_virtual_points() {
+            if virtual_point.points.iter().all(|point| names.contains(point)) {
+                names.push(virtual_point.name.clone());
+            }
+        }
+        names
+    }
+}
+
+/// Parses the match_threshold of a configuration: the minimum fraction of its visible_points that must be in the C3D
+/// for the configuration to be selected automatically, between 0 and 1.
+pub(crate) fn parse_match_threshold(value: &Value, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<f64> {
+    let threshold = parse_float(value, path.clone(), errors)?;
+    if !(0.0..=1.0).contains(&threshold) {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("The threshold must be between 0 and 1, found {}. Ignoring it", threshold)));
+        return None;
+    }
+    Some(threshold)
+}
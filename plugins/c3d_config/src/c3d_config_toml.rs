@@ -4,17 +4,37 @@ use bevy::reflect::TypePath;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 use toml::{Value, map::Map};
 
+use crate::c3d_config_camera::{parse_camera, parse_views, CameraView};
+use crate::c3d_config_color::{parse_color, ConfigColor};
+use crate::c3d_config_color_map::{parse_color_map, ColorMap};
+use crate::c3d_config_defaults::{parse_defaults, Defaults};
+use crate::c3d_config_error::*;
+use crate::c3d_config_include::include_files;
+use crate::c3d_config_metadata::{parse_metadata, ConfigMetadata};
+use crate::c3d_config_selection::parse_match_threshold;
+use crate::c3d_config_virtual::{parse_virtual_points, VirtualPoint};
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Config {
-    visible_points: Option<Vec<String>>, // Contains a regex for each point that should be visible
-    joins: Option<Vec<(Vec<String>, JoinShape)>>, // Contains a list of joins between points and the shape of the join
-    vectors: Option<HashMap<String, Vec<(String, f64)>>>, // Map where the key is the point and the value are the vectors fixed to that point, with their name and the scale
-    point_color: Option<Vec<u8>>,
-    join_color: Option<Vec<u8>>,
-    line_thickness: Option<f64>,
-    point_size: Option<f64>,
+    pub(crate) visible_points: Option<Vec<String>>, // Contains a regex for each point that should be visible
+    pub(crate) joins: Option<Vec<(Vec<String>, JoinShape)>>, // Contains a list of joins between points and the shape of the join
+    pub(crate) surfaces: Option<Vec<Surface>>, // Polygons filled between 3 or more points
+    pub(crate) vectors: Option<HashMap<String, Vec<(String, f64)>>>, // Map where the key is the point and the value are the vectors fixed to that point, with their name and the scale
+    pub(crate) point_color: Option<ConfigColor>,
+    pub(crate) join_color: Option<ConfigColor>,
+    pub(crate) line_thickness: Option<f64>,
+    pub(crate) point_size: Option<f64>,
+    pub(crate) camera: Option<CameraView>, // View of the camera when the configuration is selected
+    pub(crate) point_color_map: Option<ColorMap>, // Color of the markers from a value of every frame. Replaces point_color
+    pub(crate) join_color_map: Option<ColorMap>, // Color of the joins from a value of every frame. Replaces join_color
+    pub(crate) required_labels: Option<Vec<String>>, // Points (as visible_points) that must be in the C3D to select the configuration automatically
+    pub(crate) match_threshold: Option<f64>, // Minimum fraction of visible_points in the C3D to select the configuration automatically
+    pub(crate) metadata: ConfigMetadata, // Title, description, order and hotkey of the configuration in the interface
+    #[serde(skip)]
+    pub(crate) matcher: PointMatcher, // Compiled regex of visible_points. Must be rebuilt when visible_points changes
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -23,6 +43,16 @@ pub enum JoinShape {
     Cylinder(f64),      // Radius
     SemiCone(f64, f64), // Radius of one end, radius of the other end
     RectangularPrism(f64, f64, Option<[String;3]>), // Width, height, vectores de orientación unitarios (Iv, Jv, Kv)
+    Capsule(f64),       // Radius. A cylinder with rounded ends
+    Ellipsoid(f64, f64), // Radius across the segment in the X and Z axes of the join. The other axis is the length of the segment
+    Spline(f64),        // Radius. A smooth tube through all the points of the join, instead of one segment per pair of points
+}
+
+/// Polygon filled between its points, in order. Its shape follows the points every frame.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Surface {
+    pub points: Vec<String>,
+    pub color: Option<ConfigColor>, // The default is a translucent gray
 }
 
 impl Config {
@@ -30,11 +60,19 @@ impl Config {
         Config {
             visible_points: None,
             joins: None,
+            surfaces: None,
             vectors: None,
             point_color: None,
             join_color: None,
             line_thickness: None,
             point_size: None,
+            camera: None,
+            point_color_map: None,
+            join_color_map: None,
+            required_labels: None,
+            match_threshold: None,
+            metadata: ConfigMetadata::default(),
+            matcher: PointMatcher::default(),
         }
     }
     pub fn get_visible_points(&self) -> Option<&Vec<String>> {
@@ -43,25 +81,46 @@ impl Config {
     pub fn get_joins(&self) -> Option<&Vec<(Vec<String>, JoinShape)>> {
         self.joins.as_ref()
     }
+    pub fn get_surfaces(&self) -> Option<&Vec<Surface>> {
+        self.surfaces.as_ref()
+    }
     pub fn get_vectors(&self) -> Option<&HashMap<String, Vec<(String, f64)>>> {
         self.vectors.as_ref()
     }
     pub fn get_vectors_for_point(&self, point: &str) -> Option<&Vec<(String, f64)> > {
         self.vectors.as_ref().and_then(|v| v.get(point))
     }
+    /// Camera of the configuration, as written in the file. Use ConfigFile::get_camera to resolve its named view.
+    pub fn get_camera(&self) -> Option<&CameraView> {
+        self.camera.as_ref()
+    }
+    pub fn get_point_color_map(&self) -> Option<&ColorMap> {
+        self.point_color_map.as_ref()
+    }
+    pub fn get_join_color_map(&self) -> Option<&ColorMap> {
+        self.join_color_map.as_ref()
+    }
+    pub fn get_required_labels(&self) -> Option<&Vec<String>> {
+        self.required_labels.as_ref()
+    }
+    pub fn get_match_threshold(&self) -> Option<f64> {
+        self.match_threshold
+    }
+    pub fn get_metadata(&self) -> &ConfigMetadata {
+        &self.metadata
+    }
     pub fn add_visible_point(&mut self, point: String) {
-        if let Some(visible_points) = &mut self.visible_points {
-            visible_points.push(point);
-        } else {
-            self.visible_points = Some(vec![point]);
-        }
+        self.add_visible_point_group(vec![point]);
     }
     pub fn add_visible_point_group(&mut self, group: Vec<String>) {
-        if let Some(visible_points) = &mut self.visible_points {
-            visible_points.extend(group);
-        } else {
-            self.visible_points = Some(group);
-        }
+        let mut visible_points = self.visible_points.take().unwrap_or_default();
+        visible_points.extend(group);
+        self.set_visible_points(Some(visible_points));
+    }
+    /// Replaces the visible points, and compiles their regex
+    pub(crate) fn set_visible_points(&mut self, visible_points: Option<Vec<String>>) {
+        self.matcher = PointMatcher::new(visible_points.as_deref().unwrap_or_default());
+        self.visible_points = visible_points;
     }
     #[deprecated(
         since = "1.0.0",
@@ -75,47 +134,86 @@ impl Config {
     }
 
     pub fn contains_point_regex(&self, label: &str) -> bool {
-        match &self.visible_points {
-            Some(points) => {
-                for point in points {
-                    let re = 
-                        if point.starts_with("_"){ point.strip_prefix("_").unwrap() }
-                        else {&("^".to_owned() + point + "$")};
-                    if regex::Regex::new(re).unwrap().is_match(label) {
-                        return true;
-                    }
-                }
-                false
-            }
-            None => false,
-        }
+        self.matcher.is_match(label)
     }
 
     pub fn get_all_points_that_match(&self, label: &str) -> Vec<String> {
-        let mut matching_points = Vec::new();
-        match &self.visible_points {
-            Some(points) => {
-                for point in points {
-                    let re = 
-                        if point.starts_with("_"){ point.strip_prefix("_").unwrap() }
-                        else {&("^".to_owned() + point + "$")};
-                    if regex::Regex::new(re).unwrap().is_match(label) {
-                        matching_points.push(point.clone());
-                    }
-                }
-            }
-            None => {}
-        }
-        matching_points
+        self.matcher.matching_points(label)
+    }
+
+    /// Labels that are visible in the configuration, in the order of `labels`
+    pub fn get_visible_labels<'a>(&self, labels: &'a [String]) -> Vec<&'a String> {
+        self.matcher.matching_labels(labels)
+    }
+}
+
+/// Regex of a list of points (see point_regex), compiled once in a single set, so matching a label checks all the points in
+/// one pass. Points that are not a valid regex match the label exactly. Each configuration has one for its visible points,
+/// and the ConfigFile one for each point group and each alias.
+#[derive(Debug, Clone, Default)]
+pub struct PointMatcher {
+    points: Vec<String>,
+    set: regex::RegexSet,
+    error: Option<String>,
+}
+
+/// Two matchers are equal if they have the same points, as the regex are built from them
+impl PartialEq for PointMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.points == other.points
     }
 }
 
-#[derive(Deserialize, Debug)]
+impl PointMatcher {
+    pub fn new(points: &[String]) -> Self {
+        let patterns = points.iter().map(|point| match point_regex(point) {
+            Ok(re) => re.as_str().to_string(),
+            Err(_) => format!("^{}$", regex::escape(point)),
+        });
+        // Every pattern is valid on its own. Only a set too big for the regex size limit fails, and then nothing matches
+        let (set, error) = match regex::RegexSet::new(patterns) {
+            Ok(set) => (set, None),
+            Err(error) => (regex::RegexSet::empty(), Some(error.to_string())),
+        };
+        PointMatcher { points: points.to_vec(), set, error }
+    }
+
+    /// Why the regex of the points could not be compiled together, if they could not. Then no label matches.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn is_match(&self, label: &str) -> bool {
+        self.set.is_match(label)
+    }
+
+    /// Points that match the label, in the order they were given
+    pub fn matching_points(&self, label: &str) -> Vec<String> {
+        self.set.matches(label).into_iter().map(|i| self.points[i].clone()).collect()
+    }
+
+    /// Labels that match any of the points, in the order of `labels`
+    pub fn matching_labels<'a>(&self, labels: &'a [String]) -> Vec<&'a String> {
+        labels.iter().filter(|label| self.set.is_match(label)).collect()
+    }
+}
+
+/// Builds the regex of a point of the configuration. By default the point must match the whole label (`^point$`).
+/// If the point starts with `_`, the rest is used as is, so it can match a part of the label.
+pub fn point_regex(point: &str) -> Result<regex::Regex, regex::Error> {
+    match point.strip_prefix("_") {
+        Some(re) => regex::Regex::new(re),
+        None => regex::Regex::new(&("^".to_owned() + point + "$")),
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct PointGroupConfig {
-    point_color: Option<Vec<u8>>,
-    join_color: Option<Vec<u8>>,
-    line_thickness: Option<f64>,
-    point_size: Option<f64>,
+    pub(crate) point_color: Option<ConfigColor>,
+    pub(crate) join_color: Option<ConfigColor>,
+    pub(crate) line_thickness: Option<f64>,
+    pub(crate) point_size: Option<f64>,
+    pub(crate) priority: Option<i64>, // Groups with higher priority are applied first. Default 0
 }
 
 impl PointGroupConfig {
@@ -125,17 +223,65 @@ impl PointGroupConfig {
             join_color: None,
             line_thickness: None,
             point_size: None,
+            priority: None,
         }
     }
+    pub fn get_priority(&self) -> i64 {
+        self.priority.unwrap_or(0)
+    }
 }
 
-#[derive(Asset, TypePath, Deserialize, Debug)]
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
 #[type_path = "conf_plugin::c3d_config::ConfigFile"]
 /// This contains the configuration of the C3D file
 pub struct ConfigFile {
-    config_name: HashMap<String, Config>,
-    point_groups: Option<HashMap<String, Vec<String>>>,
-    point_groups_config: Option<HashMap<String, PointGroupConfig>>,
+    pub(crate) config_name: HashMap<String, Config>,
+    pub(crate) point_groups: Option<HashMap<String, Vec<String>>>,
+    pub(crate) point_groups_config: Option<HashMap<String, PointGroupConfig>>,
+    pub(crate) aliases: Option<HashMap<String, Vec<String>>>, // Map where the key is the canonical name of a point and the value are the labels (or regex) it has in the C3D files
+    pub(crate) views: Option<HashMap<String, CameraView>>, // Named views of the camera, that the configurations can use
+    #[serde(default)]
+    pub(crate) defaults: Defaults, // Fallback styles and unit scale of [defaults]
+    #[serde(default)]
+    pub(crate) virtual_points: Vec<VirtualPoint>, // Points computed from other points, in the order of the file
+    #[serde(skip)]
+    pub(crate) point_groups_order: Vec<String>, // Names of the point groups, in the order they were added (the order of the file)
+    #[serde(skip)]
+    pub(crate) point_group_matchers: HashMap<String, PointMatcher>, // Compiled regex of each point group. Built by add_point_group
+    #[serde(skip)]
+    pub(crate) alias_matchers: HashMap<String, PointMatcher>, // Compiled regex of the labels of each alias. Built by add_alias
+    #[serde(skip)]
+    diagnostics: Vec<ConfigError>,
+}
+
+/// Two ConfigFiles are equal if they define the same configurations (with all their fields, metadata and surfaces included)
+/// and point groups (in the same order, as it sets their precedence). Diagnostics are not compared.
+/// The fields are destructured, so a new field must be added here to compile: apply_config_changes relies on this
+/// comparison to skip the reloads that change nothing.
+impl PartialEq for ConfigFile {
+    fn eq(&self, other: &Self) -> bool {
+        let ConfigFile {
+            config_name,
+            point_groups,
+            point_groups_config,
+            aliases,
+            views,
+            defaults,
+            virtual_points,
+            point_groups_order,
+            point_group_matchers: _, // Built from the point groups
+            alias_matchers: _, // Built from the aliases
+            diagnostics: _,
+        } = self;
+        *config_name == other.config_name &&
+        *point_groups == other.point_groups &&
+        *point_groups_config == other.point_groups_config &&
+        *point_groups_order == other.point_groups_order &&
+        *aliases == other.aliases &&
+        *views == other.views &&
+        *defaults == other.defaults &&
+        *virtual_points == other.virtual_points
+    }
 }
 
 impl ConfigFile {
@@ -144,9 +290,32 @@ impl ConfigFile {
             config_name: HashMap::new(),
             point_groups: None,
             point_groups_config: None,
+            aliases: None,
+            views: None,
+            defaults: Defaults::default(),
+            virtual_points: Vec::new(),
+            point_groups_order: Vec::new(),
+            point_group_matchers: HashMap::new(),
+            alias_matchers: HashMap::new(),
+            diagnostics: Vec::new(),
         }
     }
 
+    /// Problems found while parsing the file that were not fatal. The offending values were ignored or replaced by a fallback.
+    pub fn get_diagnostics(&self) -> &Vec<ConfigError> {
+        &self.diagnostics
+    }
+
+    pub(crate) fn add_diagnostics(&mut self, diagnostics: Vec<ConfigError>) {
+        self.diagnostics.extend(diagnostics);
+    }
+
+    /// Sets the position of the diagnostics in the TOML source of the configuration, and sorts them by it
+    pub(crate) fn locate_diagnostics(&mut self, content: &str) {
+        locate_diagnostics(&mut self.diagnostics, content);
+        self.diagnostics.sort_by_key(|d| (d.line, d.column));
+    }
+
     pub fn get_config_map(&self) -> &HashMap<String, Config> {
         &self.config_name
     }
@@ -163,12 +332,20 @@ impl ConfigFile {
         self.config_name.values().collect()
     }
 
+    /// Names of the configurations, sorted by their order (see ConfigMetadata::order) and then by name
     pub fn get_all_config_names(&self) -> Vec<String> {
         let mut configs: Vec<String> = self.config_name.keys().cloned().collect();
-        configs.sort();
+        configs.sort_by_cached_key(|name| {
+            let order = self.config_name[name].metadata.order;
+            (order.is_none(), order, name.clone())
+        });
         configs
     }
 
+    pub fn get_point_groups(&self) -> Option<&HashMap<String, Vec<String>>> {
+        self.point_groups.as_ref()
+    }
+
     pub fn get_point_group(&self, point_group_name: &str) -> Option<&Vec<String>> {
         match &self.point_groups {
             Some(point_groups) => point_groups.get(point_group_name),
@@ -176,75 +353,122 @@ impl ConfigFile {
         }
     }
 
-    /// Searches for the color of a point in the config file. Returns the point_color config if exists, if not, the default config color, and if it is not set, None.
-    /// If a point is in more than one point group, the first one found will be used. The order of the point groups is not guaranteed.
-    pub fn get_point_color(&self, label: &str, config: &str) -> Option<Vec<u8>> {
-        // First check if the point is in a point group
-        if let Some(point_groups) = &self.point_groups {
-            for (group_name, points) in point_groups.iter() {
-                if points.contains(&label.to_string()) {
-                    if let Some(point_group_config) = self.point_groups_config.as_ref().and_then(|c| c.get(group_name)) {
-                        return point_group_config.point_color.clone();
-                    }
-                }
-            }
+    /// Names of the point groups, in the order of the file
+    pub fn get_point_groups_order(&self) -> &Vec<String> {
+        &self.point_groups_order
+    }
+
+    /// Names of the point groups that contain the label (matched by the PointMatcher of each group), in the order of the file
+    pub fn get_point_groups_containing(&self, label: &str) -> Vec<&String> {
+        let names = self.label_names(label);
+        self.point_groups_order
+            .iter()
+            .filter(|group_name| self.point_group_matchers.get(*group_name).is_some_and(|matcher|
+                names.iter().any(|name| matcher.is_match(name))
+            ))
+            .collect()
+    }
+
+    pub fn get_aliases(&self) -> Option<&HashMap<String, Vec<String>>> {
+        self.aliases.as_ref()
+    }
+
+    pub fn add_alias(&mut self, canonical_name: String, labels: Vec<String>) {
+        self.alias_matchers.insert(canonical_name.clone(), PointMatcher::new(&labels));
+        self.aliases.get_or_insert_with(HashMap::new).insert(canonical_name, labels);
+    }
+
+    /// Canonical name of a label of the C3D, if it matches one of the labels of an alias. The labels of an alias are matched as
+    /// the points of a point group (see PointMatcher). If several aliases match, the first one in alphabetical order is used.
+    pub fn get_canonical_name(&self, label: &str) -> Option<&String> {
+        self.alias_matchers
+            .iter()
+            .filter(|(_, matcher)| matcher.is_match(label))
+            .map(|(canonical_name, _)| canonical_name)
+            .min()
+    }
+
+    /// Label of the C3D for a point of the configuration. If the point is the canonical name of an alias, it is the first of the
+    /// labels that matches the alias. Otherwise, or if no label matches, the point itself.
+    pub fn resolve_label(&self, point: &str, labels: &[String]) -> String {
+        self.alias_matchers
+            .get(point)
+            .and_then(|matcher| labels.iter().find(|label| matcher.is_match(label)))
+            .cloned()
+            .unwrap_or_else(|| point.to_string())
+    }
+
+    /// Names a label can have in the configuration: the label itself, and its canonical name if it has one
+    pub(crate) fn label_names<'a>(&'a self, label: &'a str) -> Vec<&'a str> {
+        let mut names = vec![label];
+        if let Some(canonical_name) = self.get_canonical_name(label) {
+            names.push(canonical_name);
         }
-        // If not, check the individual config
-        self.config_name.get(config).and_then(|c| c.point_color.clone()).or_else(|| None)        
+        names
+    }
+
+    /// Configs of the point groups that contain all the labels, in order of precedence: the highest priority first and, with
+    /// the same priority, in the order of `[point_groups]`
+    fn point_group_configs_containing(&self, labels: &[&str]) -> Vec<&PointGroupConfig> {
+        let names = labels.iter().map(|label| self.label_names(label)).collect::<Vec<_>>();
+        let mut group_configs: Vec<&PointGroupConfig> = self.point_groups_order
+            .iter()
+            .filter(|group_name| self.point_group_matchers.get(*group_name).is_some_and(|matcher|
+                names.iter().all(|label_names| label_names.iter().any(|name| matcher.is_match(name)))
+            ))
+            .filter_map(|group_name| self.point_groups_config.as_ref().and_then(|c| c.get(group_name)))
+            .collect();
+        group_configs.sort_by_key(|group_config| std::cmp::Reverse(group_config.get_priority()));
+        group_configs
+    }
+
+    /// Searches for the color of a point in the config file. Returns the point_color of the first point group of the point that sets it,
+    /// if not, the default config color, and if it is not set, None.
+    /// Point groups are checked by priority, and with the same priority, in the order of `[point_groups]`. Each style is resolved on its own,
+    /// so a group can set the color of a point and another group its size.
+    pub fn get_point_color(&self, label: &str, config: &str) -> Option<ConfigColor> {
+        // First check the point groups of the point
+        self.point_group_configs_containing(&[label])
+            .iter()
+            .find_map(|group_config| group_config.point_color)
+            // If not, check the individual config
+            .or_else(|| self.config_name.get(config).and_then(|c| c.point_color))
     }
 
-    /// Searches for the size of a point in the config file. Returns the point_color config if exists, if not, the default config color, and if it is not set, None.
-    /// If a point is in more than one point group, the first one found will be used. The order of the point groups is not guaranteed.
+    /// Searches for the size of a point in the config file. Returns the point_size of the first point group of the point that sets it,
+    /// if not, the default config size, and if it is not set, None. See get_point_color for the order of the point groups.
     pub fn get_point_size (&self, label: &str, config: &str) -> Option<f64> {
-        // First check if the point is in a point group
-        if let Some(point_groups) = &self.point_groups {
-            for (group_name, points) in point_groups.iter() {
-                if points.contains(&label.to_string()) {
-                    if let Some(point_group_config) = self.point_groups_config.as_ref().and_then(|c| c.get(group_name)) {
-                        return point_group_config.point_size;
-                    }
-                }
-            }
-        }
-        // If not, check the individual config
-        self.config_name.get(config).and_then(|c| c.point_size).or_else(|| None)
+        self.point_group_configs_containing(&[label])
+            .iter()
+            .find_map(|group_config| group_config.point_size)
+            .or_else(|| self.config_name.get(config).and_then(|c| c.point_size))
     }
 
-    /// Searches for the thickness of a join between two points in the config file. Returns the line_thickness config if exists, if not, the default config color, and if it is not set, None.
+    /// Searches for the thickness of a join between two points in the config file. Returns the line_thickness of the first point group that
+    /// contains both points and sets it, if not, the default config thickness, and if it is not set, None. See get_point_color for the order of the point groups.
     /// The order between the two points does not matter.
     pub fn get_line_thickness(&self, point1: &str, point2: &str, config: &str) -> Option<f64> {
-        // First check if the points are in a point group
-        if let Some(point_groups) = &self.point_groups {
-            for (group_name, points) in point_groups.iter() {
-                if points.contains(&point1.to_string()) && points.contains(&point2.to_string()) {
-                    if let Some(point_group_config) = self.point_groups_config.as_ref().and_then(|c| c.get(group_name)) {
-                        return point_group_config.line_thickness;
-                    }
-                }
-            }
-        }
-        // If not, check the individual config
-        self.config_name.get(config).and_then(|c| c.line_thickness).or_else(|| None)
+        self.point_group_configs_containing(&[point1, point2])
+            .iter()
+            .find_map(|group_config| group_config.line_thickness)
+            .or_else(|| self.config_name.get(config).and_then(|c| c.line_thickness))
     }
 
-    /// Searches for the color of a join between two points in the config file. Returns the join_color config if exists, if not, the default config color, and if it is not set, None.
+    /// Searches for the color of a join between two points in the config file. Returns the join_color of the first point group that
+    /// contains both points and sets it, if not, the default config color, and if it is not set, None. See get_point_color for the order of the point groups.
     /// The order between the two points does not matter.
-    pub fn get_join_color(&self, point1: &str, point2: &str, config: &str) -> Option<Vec<u8>> {
-        // First check if the points are in a point group
-        if let Some(point_groups) = &self.point_groups {
-            for (group_name, points) in point_groups.iter() {
-                if points.contains(&point1.to_string()) && points.contains(&point2.to_string()) {
-                    if let Some(point_group_config) = self.point_groups_config.as_ref().and_then(|c| c.get(group_name)) {
-                        return point_group_config.join_color.clone();
-                    }
-                }
-            }
-        }
-        // If not, check the individual config
-        self.config_name.get(config).and_then(|c| c.join_color.clone()).or_else(|| None)
+    pub fn get_join_color(&self, point1: &str, point2: &str, config: &str) -> Option<ConfigColor> {
+        self.point_group_configs_containing(&[point1, point2])
+            .iter()
+            .find_map(|group_config| group_config.join_color)
+            .or_else(|| self.config_name.get(config).and_then(|c| c.join_color))
     }
 
     pub fn add_point_group(&mut self, point_group_name: String, points: Vec<String>) {
+        if !self.point_groups_order.contains(&point_group_name) {
+            self.point_groups_order.push(point_group_name.clone());
+        }
+        self.point_group_matchers.insert(point_group_name.clone(), PointMatcher::new(&points));
         if let Some(point_groups) = &mut self.point_groups {
             point_groups.insert(point_group_name, points);
         } else {
@@ -275,17 +499,26 @@ impl ConfigFile {
         }
     }
 
+    /// Checks if a label of the C3D is visible in a configuration, by itself or by its canonical name (see get_canonical_name)
     pub fn contains_point_regex(&self, config: &str, label: &str) -> bool {
         match self.config_name.get(config) {
-            Some(config) => config.contains_point_regex(label),            
+            Some(config) => self.label_names(label).iter().any(|name| config.contains_point_regex(name)),
             None => false,
         }
     }
 
+    /// Labels of the C3D that are visible in a configuration (see contains_point_regex), in the order of `labels`
+    pub fn get_visible_labels<'a>(&self, config: &str, labels: &'a [String]) -> Vec<&'a String> {
+        match self.config_name.get(config) {
+            Some(config) => labels.iter().filter(|label| self.label_names(label).iter().any(|name| config.contains_point_regex(name))).collect(),
+            None => Vec::new(),
+        }
+    }
+
     pub fn get_all_points_that_match(&self, config: &str, label: &str) -> Vec<String> {
         let matching_points = match self.config_name.get(config) {
             Some(config) => {
-                config.get_all_points_that_match(label)
+                self.label_names(label).iter().flat_map(|name| config.get_all_points_that_match(name)).collect()
             }
             None => { Vec::new() }
         };
@@ -295,7 +528,7 @@ impl ConfigFile {
     pub fn get_all_configs_that_contain_point(&self, label: &str) -> Vec<&Config> {
         let mut matching_configs = Vec::new();
         for config in self.config_name.values() {
-            if config.contains_point_regex(label) {
+            if self.label_names(label).iter().any(|name| config.contains_point_regex(name)) {
                 matching_configs.push(config);
             }
         }
@@ -305,420 +538,976 @@ impl ConfigFile {
     pub fn get_all_config_names_that_contain_point(&self, label: &str) -> Vec<String> {
         let mut matching_configs = Vec::new();
         for (config_name, config) in self.config_name.iter() {
-            if config.contains_point_regex(label) {
+            if self.label_names(label).iter().any(|name| config.contains_point_regex(name)) {
                 matching_configs.push(config_name.clone());
             }
         }
         matching_configs
     }
 
+    /// Reports the point groups, aliases, visible_points and required_labels whose regex are too big to be compiled together
+    /// (see PointMatcher). No label matches them.
+    fn check_matchers(&self, errors: &mut Vec<ConfigError>) {
+        let mut report = |path: Vec<ConfigKey>, matcher: &PointMatcher| {
+            if let Some(error) = matcher.error() {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue,
+                    path,
+                    format!("The points are too many to match them together, no label will match them: {}", error),
+                ));
+            }
+        };
+        for name in &self.point_groups_order {
+            if let Some(matcher) = self.point_group_matchers.get(name) {
+                report(vec!["point_groups".into(), name.into()], matcher);
+            }
+        }
+        let mut aliases = self.alias_matchers.iter().collect::<Vec<_>>();
+        aliases.sort_by_key(|(name, _)| *name);
+        for (name, matcher) in aliases {
+            report(vec!["aliases".into(), name.into()], matcher);
+        }
+        for name in self.get_all_config_names() {
+            let config = &self.config_name[&name];
+            report(vec![(&name).into(), "visible_points".into()], &config.matcher);
+            if let Some(required_labels) = &config.required_labels {
+                report(vec![(&name).into(), "required_labels".into()], &PointMatcher::new(required_labels));
+            }
+        }
+    }
 }
 
 pub fn merge_configs(base: &Config, override_config: &PointGroupConfig) -> Config {
     Config {
-        point_color: override_config.point_color.clone().or_else(|| base.point_color.clone()),
-        join_color: override_config.join_color.clone().or_else(|| base.join_color.clone()),
+        point_color: override_config.point_color.or(base.point_color),
+        join_color: override_config.join_color.or(base.join_color),
         line_thickness: override_config.line_thickness.or(base.line_thickness),
         point_size: override_config.point_size.or(base.point_size),
         visible_points: base.visible_points.clone(),
         joins: base.joins.clone(),
+        surfaces: base.surfaces.clone(),
         vectors: base.vectors.clone(),
+        camera: base.camera.clone(),
+        point_color_map: base.point_color_map.clone(),
+        join_color_map: base.join_color_map.clone(),
+        required_labels: base.required_labels.clone(),
+        match_threshold: base.match_threshold,
+        metadata: base.metadata.clone(),
+        matcher: base.matcher.clone(),
+    }
+}
+
+/// Builds a configuration that extends another one:
+/// - point_color, join_color, line_thickness and point_size: the value of the child, or the one of the parent if the child does not set it.
+/// - visible_points, joins and surfaces: the ones of the parent, followed by the ones of the child that the parent does not have.
+/// - vectors: the ones of the parent and the child. If both define the same vector of the same point, the scale of the child is used.
+/// - camera: the fields of the camera of the child, and the ones of the parent that the child does not set.
+/// - point_color_map, join_color_map, required_labels and match_threshold: the one of the child, or the one of the parent if
+///   the child does not set it.
+/// - title, description, order and hotkey: the ones of the child. They are not inherited, as they identify the configuration.
+pub fn inherit_config(parent: &Config, child: &Config) -> Config {
+    let mut config = parent.clone();
+    let new_points = child.visible_points.iter().flatten()
+        .filter(|point| !parent.visible_points.as_ref().is_some_and(|points| points.contains(point)))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !new_points.is_empty() {
+        config.add_visible_point_group(new_points);
+    }
+    for join in child.joins.iter().flatten() {
+        if !parent.joins.as_ref().is_some_and(|joins| joins.contains(join)) {
+            config.joins.get_or_insert_with(Vec::new).push(join.clone());
+        }
+    }
+    for surface in child.surfaces.iter().flatten() {
+        if !parent.surfaces.as_ref().is_some_and(|surfaces| surfaces.contains(surface)) {
+            config.surfaces.get_or_insert_with(Vec::new).push(surface.clone());
+        }
     }
+    for (point, child_vectors) in child.vectors.iter().flatten() {
+        let vectors = config.vectors.get_or_insert_with(HashMap::new).entry(point.clone()).or_default();
+        for (vector, scale) in child_vectors {
+            match vectors.iter_mut().find(|(name, _)| name == vector) {
+                Some(existing) => existing.1 = *scale,
+                None => vectors.push((vector.clone(), *scale)),
+            }
+        }
+    }
+    if child.vectors.is_some() && config.vectors.is_none() {
+        config.vectors = Some(HashMap::new());
+    }
+    config.point_color = child.point_color.or(config.point_color);
+    config.join_color = child.join_color.or(config.join_color);
+    config.line_thickness = child.line_thickness.or(config.line_thickness);
+    config.point_size = child.point_size.or(config.point_size);
+    config.camera = match (&child.camera, &parent.camera) {
+        (Some(child_camera), Some(parent_camera)) => Some(child_camera.with_defaults(parent_camera)),
+        (child_camera, parent_camera) => child_camera.clone().or(parent_camera.clone()),
+    };
+    config.point_color_map = child.point_color_map.clone().or(config.point_color_map);
+    config.join_color_map = child.join_color_map.clone().or(config.join_color_map);
+    config.required_labels = child.required_labels.clone().or(config.required_labels);
+    config.match_threshold = child.match_threshold.or(config.match_threshold);
+    config.metadata = child.metadata.clone();
+    config
 }
 
-fn read_config(file_or_string: &str, from_file: bool) -> Result<HashMap<String, Value>, Box<dyn std::error::Error>> {
-    let content = if from_file {fs::read_to_string(file_or_string)?} else {file_or_string.to_string()};
-    let config: HashMap<String, Value> = toml::from_str(&content)?;
-    Ok(config)
+fn read_config(file_or_string: &str, from_file: bool) -> Result<String, ConfigError> {
+    if from_file {
+        fs::read_to_string(file_or_string).map_err(|e| 
+            ConfigError::new(ConfigErrorKind::Io, Vec::new(), format!("Could not read {}: {}", file_or_string, e))
+        )
+    } else {
+        Ok(file_or_string.to_string())
+    }
 }
 
-pub fn parse_config(file_or_string: &str, from_file: bool) -> Result<ConfigFile, String> {
-    let config = read_config(file_or_string, from_file).unwrap_or(HashMap::new());
+/// Parses a TOML configuration, from a file or from its content.
+/// Returns an error if the file cannot be read or is not valid TOML. Problems in the values of the configuration are not
+/// fatal: they are collected in ConfigFile::get_diagnostics, with their key path and position in the file.
+///
+/// The files in `include` are merged before the configuration (see c3d_config_include). They are relative to the
+/// directory of the file, or to the working directory if the configuration is given by its content.
+pub fn parse_config(file_or_string: &str, from_file: bool) -> Result<ConfigFile, ConfigError> {
+    let content = read_config(file_or_string, from_file)?;
+    let config = toml_config_table(&content)?;
+    let mut errors = Vec::new();
+    let path = if from_file { Path::new(file_or_string) } else { Path::new("") };
+    let config = include_files(config, path, &mut errors);
+    let mut config_file = parse_config_table(config)?;
+    config_file.add_diagnostics(errors);
+    config_file.locate_diagnostics(&content);
+    Ok(config_file)
+}
+
+/// Top level table of a TOML configuration. Returns an error if it is not valid TOML.
+pub(crate) fn toml_config_table(content: &str) -> Result<HashMap<String, Value>, ConfigError> {
+    toml::from_str(content).map_err(|e| {
+        let error = ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), e.message());
+        match e.span() {
+            Some(span) => error.with_offset(content, span.start),
+            None => error,
+        }
+    })
+}
+
+/// Sets the line and column of the diagnostics, searching their key path in the TOML source
+fn locate_diagnostics(diagnostics: &mut [ConfigError], content: &str) {
+    let Ok(document) = toml_edit::ImDocument::parse(content) else {
+        return;
+    };
+    for diagnostic in diagnostics.iter_mut().filter(|d| d.line.is_none()) {
+        let mut item = document.as_item();
+        let mut span = None;
+        for key in &diagnostic.key_path {
+            let next = match key {
+                ConfigKey::Key(key) => {
+                    if let Some(key_span) = item.as_table_like().and_then(|t| t.key(key)).and_then(|k| k.span()) {
+                        span = Some(key_span);
+                    }
+                    item.get(key.as_str())
+                }
+                ConfigKey::Index(index) => item.get(*index),
+            };
+            match next {
+                Some(next) => {
+                    item = next;
+                    if let Some(item_span) = item.span() {
+                        span = Some(item_span);
+                    }
+                }
+                None => break,
+            }
+        }
+        if let Some(span) = span {
+            *diagnostic = diagnostic.clone().with_offset(content, span.start);
+        }
+    }
+}
+
+/// Builds a ConfigFile from the top level table of a configuration, with the layout of the TOML format.
+/// Other front ends (as YAML) translate their documents to this layout, so every format yields the same ConfigFile.
+pub(crate) fn parse_config_table(config: HashMap<String, Value>) -> Result<ConfigFile, ConfigError> {
     let mut config_file = ConfigFile::default();
+    let mut errors = Vec::new();
 
     // Caution: The order of the keys in the config file is not guaranteed, because it is a hashmap.
     // We need to parse the point groups first, as they are used in the individual configs. 
-    config.get("point_groups").and_then(|v| v.as_table()).map(|groups| {
-        for (group_name, group_value) in groups {
-            if let Value::Array(points) = group_value {
-                let points_vec: Vec<String> = points
-                    .iter()
-                    .filter_map(|v| match v {
-                        Value::String(s) => Some(s.to_string()),
-                        _ => None,
-                    })
-                    .collect();
-                config_file.add_point_group(group_name.clone(), points_vec);
+    match config.get("point_groups") {
+        Some(Value::Table(groups)) => {
+            for (group_name, group_value) in groups {
+                if let Value::Array(points) = group_value {
+                    let mut points_vec = Vec::new();
+                    for (i, point) in points.iter().enumerate() {
+                        let path = vec!["point_groups".into(), group_name.into(), i.into()];
+                        match point {
+                            Value::String(s) => {
+                                if check_point_regex(s, path, &mut errors) {
+                                    points_vec.push(s.to_string());
+                                }
+                            }
+                            _ => errors.push(ConfigError::new(
+                                ConfigErrorKind::InvalidValue,
+                                path,
+                                format!("Expected a point label, found {}", point.type_str()),
+                            )),
+                        }
+                    }
+                    config_file.add_point_group(group_name.clone(), points_vec);
+                } else {
+                    errors.push(ConfigError::new(
+                        ConfigErrorKind::InvalidValue,
+                        vec!["point_groups".into(), group_name.into()],
+                        format!("Expected an array of points, found {}", group_value.type_str()),
+                    ));
+                }
             }
         }
-    });
+        Some(other) => errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            vec!["point_groups".into()],
+            format!("Expected a table of point groups, found {}", other.type_str()),
+        )),
+        None => {}
+    }
+
+    match config.get("aliases") {
+        Some(Value::Table(aliases)) => {
+            for (canonical_name, labels) in aliases {
+                let path = vec!["aliases".into(), canonical_name.into()];
+                let labels = match labels {
+                    Value::String(label) => vec![label.clone()],
+                    Value::Array(labels) => labels.iter().enumerate().filter_map(|(i, label)| match label {
+                        Value::String(label) => Some(label.clone()),
+                        _ => {
+                            errors.push(ConfigError::new(
+                                ConfigErrorKind::InvalidValue,
+                                [path.clone(), vec![i.into()]].concat(),
+                                format!("Expected a label, found {}", label.type_str()),
+                            ));
+                            None
+                        }
+                    }).collect(),
+                    _ => {
+                        errors.push(ConfigError::new(
+                            ConfigErrorKind::InvalidValue,
+                            path,
+                            format!("Expected a label or an array of labels, found {}", labels.type_str()),
+                        ));
+                        continue;
+                    }
+                };
+                config_file.add_alias(canonical_name.clone(), labels);
+            }
+        }
+        Some(other) => errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            vec!["aliases".into()],
+            format!("Expected a table of aliases, found {}", other.type_str()),
+        )),
+        None => {}
+    }
+
+    match config.get("virtual_points") {
+        Some(Value::Table(virtual_points)) => {
+            for virtual_point in parse_virtual_points(virtual_points, &mut errors) {
+                config_file.add_virtual_point(virtual_point);
+            }
+        }
+        Some(other) => errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            vec!["virtual_points".into()],
+            format!("Expected a table of virtual points, found {}", other.type_str()),
+        )),
+        None => {}
+    }
+
+    match config.get("views") {
+        Some(Value::Table(views)) => {
+            for (name, view) in parse_views(views, &mut errors) {
+                config_file.add_view(name, view);
+            }
+        }
+        Some(other) => errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            vec!["views".into()],
+            format!("Expected a table of views, found {}", other.type_str()),
+        )),
+        None => {}
+    }
+
+    match config.get("defaults") {
+        Some(Value::Table(defaults)) => config_file.set_defaults(parse_defaults(defaults, &mut errors)),
+        Some(other) => errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            vec!["defaults".into()],
+            format!("Expected a table of default values, found {}", other.type_str()),
+        )),
+        None => {}
+    }
 
+    let mut config_tables: HashMap<String, Map<String, Value>> = HashMap::new();
     for (key, value) in config {
         match key.as_str() {
-            "point_groups" => {} // Already parsed
+            "point_groups" | "aliases" | "virtual_points" | "views" | "defaults" | "include" => {} // Already parsed
             _ => {
                 if let Value::Table(sub_table) = value {  // En el toml especificamos point_group.config, que nos crea una tabla con el nombre del point_group, con un campo config, que es el que nos interesa
                     if let Some(config) = sub_table.get("config") {
-                        let point_group_config = parse_point_group_config(
-                            config
-                                .as_table()
-                                .ok_or("Error parsing point group config")?
-                                .clone(),
-                        )?;
-                        config_file.add_point_group_config(key, point_group_config);
+                        match config.as_table() {
+                            Some(config) => {
+                                let point_group_config = parse_point_group_config(&key, config, &mut errors);
+                                config_file.add_point_group_config(key, point_group_config);
+                            }
+                            None => errors.push(ConfigError::new(
+                                ConfigErrorKind::InvalidValue,
+                                vec![(&key).into(), "config".into()],
+                                format!("Expected a table with the point group config, found {}", config.type_str()),
+                            )),
+                        }
                     }  else {
-                        let config = parse_individual_config(sub_table, &config_file.point_groups)?;
-                        config_file.config_name.insert(key, config);
+                        config_tables.insert(key, sub_table);
                     }
+                } else {
+                    errors.push(ConfigError::new(
+                        ConfigErrorKind::InvalidValue,
+                        vec![(&key).into()],
+                        format!("Expected a configuration table, found {}", value.type_str()),
+                    ));
                 }
             }
         }
     }
 
-    // Now merge the point group configs with the individual configs
-    for (group_name, group_config) in config_file.point_groups_config.iter().flatten() {
-        if let Some(point_groups) = &config_file.point_groups {
-            if let Some(points) = point_groups.get(group_name) {
-                for point in points {
-                    if let Some(config) = config_file.config_name.get_mut(point) {
-                        *config = merge_configs(config, group_config);
-                    }
-                }
-            }
-        }
+    // Parents are parsed before their children, as a child is built on top of the resolved config of its parent
+    let parents = config_parents(&config_tables, &mut errors);
+    let mut config_names = config_tables.keys().cloned().collect::<Vec<_>>();
+    config_names.sort();
+    for config_name in config_names {
+        parse_config_with_parents(&config_name, &mut config_tables, &parents, &mut config_file, &mut errors);
     }
 
+    config_file.check_hotkeys(&mut errors);
+    config_file.check_matchers(&mut errors);
+
+    // The point group configs are not merged into the individual configs: a point can be in several groups, and groups can be
+    // regex, so the style of each point and join is resolved when it is requested (see get_point_color and get_join_color)
 
+    config_file.diagnostics.extend(errors);
     Ok(config_file)
 }
 
+/// Reads the `extends` key of every configuration. Unknown parents and configurations that are part of an inheritance
+/// cycle are reported, and their `extends` is ignored. The configurations are checked by name, so the diagnostics are
+/// always in the same order.
+fn config_parents(config_tables: &HashMap<String, Map<String, Value>>, errors: &mut Vec<ConfigError>) -> HashMap<String, String> {
+    let mut names = config_tables.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut parents = HashMap::new();
+    for &name in &names {
+        match config_tables[name].get("extends") {
+            None => {}
+            Some(Value::String(parent)) if config_tables.contains_key(parent) => { parents.insert(name.clone(), parent.clone()); }
+            Some(Value::String(parent)) => errors.push(ConfigError::new(
+                ConfigErrorKind::UnknownReference,
+                vec![name.into(), "extends".into()],
+                format!("Configuration {:?} not found. Ignoring extends", parent),
+            )),
+            Some(other) => errors.push(ConfigError::new(
+                ConfigErrorKind::InvalidValue,
+                vec![name.into(), "extends".into()],
+                format!("Expected the name of a configuration, found {}", other.type_str()),
+            )),
+        }
+    }
+
+    let mut in_cycle = Vec::new();
+    for name in names.into_iter().filter(|name| parents.contains_key(*name)) {
+        let mut chain = vec![name.clone()];
+        while let Some(parent) = parents.get(chain.last().unwrap()) {
+            if parent == name {
+                chain.push(parent.clone());
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue,
+                    vec![name.into(), "extends".into()],
+                    format!("Inheritance cycle: {}. Ignoring extends", chain.join(" -> ")),
+                ));
+                in_cycle.push(name.clone());
+                break;
+            }
+            if chain.contains(parent) {
+                break; // The cycle does not contain this config, it will be reported by the configs in the cycle
+            }
+            chain.push(parent.clone());
+        }
+    }
+    for name in in_cycle {
+        parents.remove(&name);
+    }
+    parents
+}
+
+/// Parses a configuration and, before it, the configurations it extends. Each configuration is parsed only once.
+fn parse_config_with_parents(
+    name: &str,
+    config_tables: &mut HashMap<String, Map<String, Value>>,
+    parents: &HashMap<String, String>,
+    config_file: &mut ConfigFile,
+    errors: &mut Vec<ConfigError>,
+) {
+    let Some(table) = config_tables.remove(name) else {
+        return; // Already parsed
+    };
+    let parent = parents.get(name);
+    if let Some(parent) = parent {
+        parse_config_with_parents(parent, config_tables, parents, config_file, errors);
+    }
+    let parent_config = parent.and_then(|parent| config_file.config_name.get(parent));
+    let config = parse_individual_config(name, table, &config_file.point_groups, &config_file.views, parent_config, errors);
+    config_file.config_name.insert(name.to_string(), config);
+}
+
+/// Parses a configuration table. If the configuration extends another one, the result is the parent config with the
+/// values of this one on top of it (see inherit_config).
 fn parse_individual_config(
+    name: &str,
     table: Map<String, Value>,
     point_groups: &Option<HashMap<String, Vec<String>>>,
-) -> Result<Config, String> {
+    views: &Option<HashMap<String, CameraView>>,
+    parent: Option<&Config>,
+    errors: &mut Vec<ConfigError>,
+) -> Config {
     let mut config = Config::default();
 
     if let Some(Value::Array(visible_points)) = table.get("visible_points") {
-        for item in visible_points.iter() {
+        let mut points = Vec::new();
+        for (i, item) in visible_points.iter().enumerate() {
+            let path = vec![name.into(), "visible_points".into(), i.into()];
             match item {
                 // normal case: Add a single point
-                Value::String(point) => config.add_visible_point(point.clone()),
+                Value::String(point) => {
+                    if check_point_regex(point, path, errors) {
+                        points.push(point.clone());
+                    }
+                },
                 // case where we want to add a group of points
-                Value::Array(group_ref) if (group_ref.len() == 1 && point_groups.is_some()) => {
-                    if let Some(Value::String(group_name)) = group_ref.get(0) {
-                        if let Some(points) = point_groups.as_ref().unwrap().get(group_name) {
-                            config.add_visible_point_group(points.clone());
-                        }
+                Value::Array(group_ref) if group_ref.len() == 1 => {
+                    if let Some(group_points) = expand_point_group(point_groups, group_ref, path, errors) {
+                        points.extend(group_points);
                     }
                 },
-                Value::Array(group_ref) => println!("Detected unexpected group reference: {:?}", group_ref),
-                _ => println!("Invalid point in visible_points: {:?}", item),
-                
+                Value::Array(group_ref) => errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue, 
+                    path, 
+                    format!("A group reference must contain only the name of the group, found {} elements", group_ref.len()),
+                )),
+                _ => errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue, 
+                    path, 
+                    format!("Expected a point label or a group reference, found {}", item.type_str()),
+                )),
             }
         }
+        if !points.is_empty() {
+            config.set_visible_points(Some(points));
+        }
     }
 
     if let Some(Value::Array(vectors)) = table.get("vectors") {
         let mut vector_map: HashMap<String, Vec<(String, f64)>> = HashMap::new();
-        for vector in vectors {
-            let mut vectors_in_map = Vec::new();
-            if let Value::Array(vector_pair) = vector {
-                if vector_pair.len() == 2 {
-                    if let Some(Value::String(point)) = vector_pair.get(0) {
-                        match vector_pair.get(1) {
-                            Some(Value::String(vector_name)) => {
-                                if vector_map.contains_key(point) {
-                                    vectors_in_map = vector_map.get(point).unwrap().clone();  // If the point already has vectors, we need to keep them
-                                }
-                                vectors_in_map.push((vector_name.clone(), 1.0));
-                                vector_map.insert(point.clone(), vectors_in_map);
-                            },
-                            Some(Value::Float(_)) | Some(Value::Integer(_)) => println!("Scale not permitted without point and vector: {:?}", vector_pair),
-                            Some(Value::Array(values)) => {
-                                vectors_in_map = vector_map.get(point).unwrap_or(&Vec::new()).to_vec();
-                                for vector in values {
-                                    match vector {
-                                        Value::String(vector) => vectors_in_map.push((vector.clone(), 1.0)),
-                                        _ => println!("Value not recognized at {vector}")
-                                    }
-                                }
-                                vector_map.insert(point.to_string(), vectors_in_map);
-                            },
-                            _ => (),
-                        }
-                    } else {
-                        println!("Invalid vector pair: {:?}", vector_pair)
+        for (i, vector) in vectors.iter().enumerate() {
+            let path = vec![name.into(), "vectors".into(), i.into()];
+            let Value::Array(vector_pair) = vector else {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue, 
+                    path, 
+                    format!("Expected [point, vector] or [point, vector, scale], found {}", vector.type_str()),
+                ));
+                continue;
+            };
+            if vector_pair.len() != 2 && vector_pair.len() != 3 {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue, 
+                    path, 
+                    format!("Expected [point, vector] or [point, vector, scale], found {} elements", vector_pair.len()),
+                ));
+                continue;
+            }
+            let Some(Value::String(point)) = vector_pair.first() else {
+                errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "The first element of a vector must be the label of the point"));
+                continue;
+            };
+            let scale = match vector_pair.get(2) {
+                Some(scale) => match as_number(scale) {
+                    Some(scale) => scale,
+                    None => {
+                        errors.push(ConfigError::new(
+                            ConfigErrorKind::InvalidValue, 
+                            vec![name.into(), "vectors".into(), i.into(), 2.into()], 
+                            format!("Expected a number as scale, found {}. Using 1.0", scale.type_str()),
+                        ));
+                        1.0
                     }
-                } else if vector_pair.len() == 3 {
-                    if let Some(Value::String(point)) = vector_pair.get(0) {
-                        match vector_pair.get(1) {
-                            Some(Value::String(vector_name)) => {
-                                if let Some(Value::Float(scale)) = vector_pair.get(2) {
-                                    if vector_map.contains_key(point) {
-                                        vectors_in_map = vector_map.get(point).unwrap().clone();  // If the point already has vectors, we need to keep them
-                                    }
-                                    vectors_in_map.push((vector_name.clone(), *scale));
-                                    vector_map.insert(point.clone(), vectors_in_map);
-                                }
-                            },
-                            Some(Value::Float(_)) | Some(Value::Integer(_)) => println!("Scale not permitted without point and vector. Maybe incorrect order. Set Point, Vector, Scale: {:?}", vector_pair),
-                            Some(Value::Array(values)) => {
-                                vectors_in_map = vector_map.get(point).unwrap_or(&Vec::new()).to_vec();
-                                let scale = vector_pair.get(2).and_then(|x| x.as_float()).unwrap_or(1.0);
-                                for vector in values {
-                                    match vector {
-                                        Value::String(vector) => vectors_in_map.push((vector.clone(), scale)),
-                                        _ => println!("Value not recognized at {vector}")
-                                    }
-                                }
-                                vector_map.insert(point.to_string(), vectors_in_map);  
-                            },
-                            _ => (),
+                },
+                None => 1.0,
+            };
+            match vector_pair.get(1) {
+                Some(Value::String(vector_name)) => {
+                    vector_map.entry(point.clone()).or_default().push((vector_name.clone(), scale));  // If the point already has vectors, we need to keep them
+                },
+                Some(Value::Array(values)) => {
+                    for (j, vector) in values.iter().enumerate() {
+                        match vector {
+                            Value::String(vector) => vector_map.entry(point.clone()).or_default().push((vector.clone(), scale)),
+                            _ => errors.push(ConfigError::new(
+                                ConfigErrorKind::InvalidValue, 
+                                vec![name.into(), "vectors".into(), i.into(), 1.into(), j.into()], 
+                                format!("Expected the label of a vector, found {}", vector.type_str()),
+                            )),
                         }
                     }
-                } else {
-                    println!("Invalid vector pair: {:?}", vector_pair);
-                }
-            } else {
-                println!("Invalid vector: {:?}", vector);
+                },
+                Some(Value::Float(_)) | Some(Value::Integer(_)) => errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue, 
+                    path, 
+                    "Scale not permitted without point and vector. Maybe incorrect order. Set Point, Vector, Scale",
+                )),
+                _ => errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "The second element of a vector must be a label or an array of labels")),
             }
         }
         config.vectors = Some(vector_map);
     }
 
+    // Prisms can be oriented with the vectors of the parent
+    let vectors_config = match parent {
+        Some(parent) => inherit_config(parent, &config),
+        None => config.clone(),
+    };
+
     if let Some(Value::Array(joins)) = table.get("joins") {
-        for join in joins {
+        for (i, join) in joins.iter().enumerate() {
+            let path = vec![name.into(), "joins".into(), i.into()];
             match join {
                 Value::Array(points) => {
-                    generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
+                    generate_expanded_points(point_groups, &mut config, points, JoinShape::Line, path, errors);
                 }
                 Value::Table(join_table) => {
-                    let shape = join_table.get("shape");
-                    if let Some(shape) = shape {
-                        match shape {
-                            Value::String(s) if s.to_lowercase() == "line" => {
-                                if let Some(Value::Array(points)) = join_table.get("points") {
-                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
-                                }
-                            }
-                            Value::Table(shapes_table) if shapes_table.contains_key("type") => {
-                                match shapes_table.get("type") {
-                                    Some(Value::String(s)) if s.to_lowercase() == "line" => {
-                                        if let Some(Value::Array(points)) = join_table.get("points") {
-                                            generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
-                                        }
-                                    }
-                                    Some(Value::String(s)) 
-                                        if (s.to_lowercase() == "cylinder" || 
-                                            s.to_lowercase() == "cilindro"
-                                    ) => {
-                                        if let Some(Value::Array(points)) = join_table.get("points") {
-                                            match shapes_table.get("radius") {
-                                                Some(Value::Float(radius)) => generate_expanded_points(point_groups, &mut config, points, JoinShape::Cylinder(*radius)),
-                                                Some(Value::Integer(radius)) => generate_expanded_points(point_groups, &mut config, points, JoinShape::Cylinder(*radius as f64)),                                      
-                                                _ => {
-                                                    println!("Cylinder join without radius: {:?}", shapes_table);
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
-                                                }
-                                            }
-                                        } else {
-                                            println!("Cylinder join without points: {:?}", join_table);
-                                        }
-                                    },
-                                    Some(Value::String(s)) 
-                                        if (s.to_lowercase() == "cone" ||
-                                            s.to_lowercase() == "cono"
-                                    ) => {
-                                        if let Some(Value::Array(points)) = join_table.get("points") {
-                                            match shapes_table.get("radius") {
-                                                Some(Value::Float(radius)) => generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(*radius, 0.0)),
-                                                Some(Value::Integer(radius)) => generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(*radius as f64, 0.0)),
-                                                _ => {
-                                                    println!("Cone join without proper radius: {:?}", shapes_table);
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(0.5, 0.0));
-                                                },
-                                            }
-                                        }
-                                    },
-                                    Some(Value::String(s)) 
-                                        if (s.to_lowercase() == "semicone" ||
-                                            s.to_lowercase() == "semicono" ||
-                                            s.to_lowercase() == "cone frustum" ||
-                                            s.to_lowercase() == "cono truncado" ||
-                                            s.to_lowercase() == "partial cone" ||
-                                            s.to_lowercase() == "cono parcial" ||
-                                            s.to_lowercase() == "truncated cone" ||
-                                            s.to_lowercase() == "cono truncado"
-                                    ) => {
-                                        if let Some(Value::Array(points)) = join_table.get("points") {
-                                            match (shapes_table.get("radius1"), shapes_table.get("radius2")) {
-                                                (Some(Value::Float(radius1)), Some(Value::Float(radius2))) => 
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(*radius1, *radius2)),
-                                                (Some(Value::Integer(radius1)), Some(Value::Integer(radius2))) => 
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(*radius1 as f64, *radius2 as f64)),
-                                                (Some(Value::Float(radius1)), Some(Value::Integer(radius2))) =>
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(*radius1, *radius2 as f64)),
-                                                (Some(Value::Integer(radius1)), Some(Value::Float(radius2))) =>
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(*radius1 as f64, *radius2)),
-                                                _ => {
-                                                    println!("SemiCone join without proper radius: {:?}", shapes_table);
-                                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
-                                                },
-                                            }
-                                        } else {
-                                            println!("SemiCone join without points: {:?}", join_table);
-                                        }
-                                    },
-                                    Some(Value::String(s))
-                                        if (s.to_lowercase() == "prisma rectangular" ||
-                                            s.to_lowercase() == "rectangular prism" ||
-                                            s.to_lowercase() == "prisma" ||
-                                            s.to_lowercase() == "prism" ||
-                                            s.to_lowercase() == "paralelepipedo" ||
-                                            s.to_lowercase() == "paralelepípedo" ||
-                                            s.to_lowercase() == "parallelepiped"
-                                    ) => {
-                                        if let Some(Value::Array(points)) = join_table.get("points") {
-                                            let width = shapes_table.get("width").and_then(Value::as_float).or_else(|| shapes_table.get("width").and_then(Value::as_integer).map(|v| v as f64));
-                                            let height = shapes_table.get("height").and_then(Value::as_float).or_else(|| shapes_table.get("height").and_then(Value::as_integer).map(|v| v as f64));
-                                            
-                                            let orientation_point = shapes_table.get("vector").and_then(|v| v.as_str());
-                                            let orientation_vectors = orientation_point
-                                                .and_then(|orientation_vectors| {
-                                                    config.get_vectors_for_point(orientation_vectors)
-                                                        .filter(|vectors| vectors.len() == 3)
-                                                        .map(|vectors| [
-                                                            vectors[0].0.clone(),
-                                                            vectors[1].0.clone(),
-                                                            vectors[2].0.clone(),
-                                                        ])
-                                                });
-                                            
-                                            if width.is_none() || height.is_none() {
-                                                println!("Rectangular prism join without proper width or height: {:?}", shapes_table);
-                                                generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
-                                                continue;
-                                            }
-                                            
-                                            let width = width.unwrap();
-                                            let height = height.unwrap();
-
-                                            match orientation_point {
-                                                Some(orientation_point) => {
-                                                    match orientation_vectors {
-                                                        Some(vectors) => generate_expanded_points(point_groups, &mut config, points, JoinShape::RectangularPrism(width, height, Some(vectors))),
-                                                        None => {
-                                                            println!("Orientation point {:?} not found in vectors", orientation_point);
-                                                            generate_expanded_points(point_groups, &mut config, points, JoinShape::RectangularPrism(width, height, None));
-                                                        }
-                                                    }
-                                                },
-                                                None => generate_expanded_points(point_groups, &mut config, points, JoinShape::RectangularPrism(width, height, None)),
-                                            }
-                                        }
-                                    },
-                                    _ => {
-                                        if let Some(Value::Array(points)) = join_table.get("points") {
-                                            generate_expanded_points(point_groups, &mut config, points, JoinShape::SemiCone(0.75, 0.25));
-                                        }
-                                        println!("Shape {:?} not implemented", shape)
-                                    },
-                                    
-                                }
-                            }
-                            _ => {
-                                if let Some(Value::Array(points)) = join_table.get("points") {
-                                    generate_expanded_points(point_groups, &mut config, points, JoinShape::Line);
-                                }
-                                println!("Shape {:?} not implemented", shape)
-                            },
-                        }
-                    }
+                    let Some(Value::Array(points)) = join_table.get("points") else {
+                        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Join without points"));
+                        continue;
+                    };
+                    let points_path = [path.clone(), vec!["points".into()]].concat();
+                    let shape_path = [path.clone(), vec!["shape".into()]].concat();
+                    let shape = match join_table.get("shape") {
+                        None => JoinShape::Line,
+                        Some(shape) => parse_join_shape(shape, &vectors_config, shape_path, errors),
+                    };
+                    generate_expanded_points(point_groups, &mut config, points, shape, points_path, errors);
                 },
-                _ => (),
+                _ => errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue, 
+                    path, 
+                    format!("Expected an array of points or a table with points and shape, found {}", join.type_str()),
+                )),
             }
         }
     }
 
-    config.point_color = table.get("point_color").and_then(|v| v.as_array()).and_then(|v| {
-        if v.len() == 3 {
-            Some(vec![v[0].as_integer().unwrap() as u8, v[1].as_integer().unwrap() as u8, v[2].as_integer().unwrap() as u8])
-        } else if v.len() == 4 {
-            Some(vec![v[0].as_integer().unwrap() as u8, v[1].as_integer().unwrap() as u8, v[2].as_integer().unwrap() as u8, v[3].as_integer().unwrap() as u8])
-        } else {
-            None
+    if let Some(Value::Array(surfaces)) = table.get("surfaces") {
+        for (i, surface) in surfaces.iter().enumerate() {
+            let path = vec![name.into(), "surfaces".into(), i.into()];
+            let (points, points_path, color) = match surface {
+                Value::Array(points) => (points, path.clone(), None),
+                Value::Table(surface_table) => {
+                    let Some(Value::Array(points)) = surface_table.get("points") else {
+                        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Surface without points"));
+                        continue;
+                    };
+                    let color = surface_table.get("color").and_then(|v| parse_color(v, [path.clone(), vec!["color".into()]].concat(), errors));
+                    (points, [path.clone(), vec!["points".into()]].concat(), color)
+                },
+                _ => {
+                    errors.push(ConfigError::new(
+                        ConfigErrorKind::InvalidValue, 
+                        path, 
+                        format!("Expected an array of points or a table with points and color, found {}", surface.type_str()),
+                    ));
+                    continue;
+                }
+            };
+            let previous_errors = errors.len();
+            let points = expand_points(point_groups, points, points_path, errors);
+            if points.len() > 2 {
+                config.surfaces.get_or_insert_with(Vec::new).push(Surface { points, color });
+            } else if errors.len() == previous_errors {
+                errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "A surface needs at least 3 points"));
+            }
         }
-    });
-    config.join_color = table.get("join_color").and_then(|v| v.as_array()).and_then(|v| {
-        if v.len() == 3 {
-            Some(vec![v[0].as_integer().unwrap() as u8, v[1].as_integer().unwrap() as u8, v[2].as_integer().unwrap() as u8])
-        } else if v.len() == 4 {
-            Some(vec![v[0].as_integer().unwrap() as u8, v[1].as_integer().unwrap() as u8, v[2].as_integer().unwrap() as u8, v[3].as_integer().unwrap() as u8])
-        } else {
-            None
+    }
+
+    config.point_color = table.get("point_color").and_then(|v| parse_color(v, vec![name.into(), "point_color".into()], errors));
+    config.join_color = table.get("join_color").and_then(|v| parse_color(v, vec![name.into(), "join_color".into()], errors));
+    config.line_thickness = table.get("line_thickness").and_then(|v| parse_float(v, vec![name.into(), "line_thickness".into()], errors));
+    config.point_size = table.get("point_size").and_then(|v| parse_float(v, vec![name.into(), "point_size".into()], errors));
+    config.camera = table.get("camera").and_then(|v| parse_camera(v, views, vec![name.into(), "camera".into()], errors));
+    config.point_color_map = table.get("point_color_map").and_then(|v| parse_color_map(v, true, vec![name.into(), "point_color_map".into()], errors));
+    config.join_color_map = table.get("join_color_map").and_then(|v| parse_color_map(v, false, vec![name.into(), "join_color_map".into()], errors));
+    config.match_threshold = table.get("match_threshold").and_then(|v| parse_match_threshold(v, vec![name.into(), "match_threshold".into()], errors));
+    match table.get("required_labels") {
+        Some(Value::Array(required_labels)) => {
+            let mut labels = Vec::new();
+            for (i, label) in required_labels.iter().enumerate() {
+                let path = vec![name.into(), "required_labels".into(), i.into()];
+                match label {
+                    Value::String(label) => {
+                        if check_point_regex(label, path, errors) {
+                            labels.push(label.clone());
+                        }
+                    }
+                    _ => errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected a point label, found {}", label.type_str()))),
+                }
+            }
+            config.required_labels = Some(labels);
         }
-    });
-    config.line_thickness = table.get("line_thickness").and_then(|v| v.as_float());
-    config.point_size = table.get("point_size").and_then(|v| v.as_float());
+        Some(other) => errors.push(ConfigError::new(
+            ConfigErrorKind::InvalidValue,
+            vec![name.into(), "required_labels".into()],
+            format!("Expected an array of point labels, found {}", other.type_str()),
+        )),
+        None => {}
+    }
+    config.metadata = parse_metadata(&table, name, errors);
+
+    match parent {
+        Some(parent) => inherit_config(parent, &config),
+        None => config,
+    }
+}
+
+/// Parses the shape of a join. Unknown or incomplete shapes are reported, and replaced by a fallback shape.
+fn parse_join_shape(shape: &Value, config: &Config, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> JoinShape {
+    match shape {
+        Value::String(s) if s.to_lowercase() == "line" => JoinShape::Line,
+        Value::Table(shapes_table) if shapes_table.contains_key("type") => {
+            match shapes_table.get("type") {
+                Some(Value::String(s)) if s.to_lowercase() == "line" => JoinShape::Line,
+                Some(Value::String(s)) 
+                    if (s.to_lowercase() == "cylinder" || 
+                        s.to_lowercase() == "cilindro"
+                ) => {
+                    match shapes_table.get("radius").and_then(as_number) {
+                        Some(radius) => JoinShape::Cylinder(radius),
+                        None => {
+                            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Cylinder join without radius. Using a line"));
+                            JoinShape::Line
+                        }
+                    }
+                },
+                Some(Value::String(s)) 
+                    if (s.to_lowercase() == "cone" ||
+                        s.to_lowercase() == "cono"
+                ) => {
+                    match shapes_table.get("radius").and_then(as_number) {
+                        Some(radius) => JoinShape::SemiCone(radius, 0.0),
+                        None => {
+                            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Cone join without proper radius. Using radius 0.5"));
+                            JoinShape::SemiCone(0.5, 0.0)
+                        },
+                    }
+                },
+                Some(Value::String(s)) 
+                    if (s.to_lowercase() == "semicone" ||
+                        s.to_lowercase() == "semicono" ||
+                        s.to_lowercase() == "cone frustum" ||
+                        s.to_lowercase() == "cono truncado" ||
+                        s.to_lowercase() == "partial cone" ||
+                        s.to_lowercase() == "cono parcial" ||
+                        s.to_lowercase() == "truncated cone" ||
+                        s.to_lowercase() == "cono truncado"
+                ) => {
+                    match (shapes_table.get("radius1").and_then(as_number), shapes_table.get("radius2").and_then(as_number)) {
+                        (Some(radius1), Some(radius2)) => JoinShape::SemiCone(radius1, radius2),
+                        _ => {
+                            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "SemiCone join without proper radius1 and radius2. Using a line"));
+                            JoinShape::Line
+                        },
+                    }
+                },
+                Some(Value::String(s))
+                    if (s.to_lowercase() == "capsule" ||
+                        s.to_lowercase() == "cápsula" ||
+                        s.to_lowercase() == "capsula"
+                ) => {
+                    match shapes_table.get("radius").and_then(as_number) {
+                        Some(radius) => JoinShape::Capsule(radius),
+                        None => {
+                            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Capsule join without radius. Using a line"));
+                            JoinShape::Line
+                        }
+                    }
+                },
+                Some(Value::String(s))
+                    if (s.to_lowercase() == "ellipsoid" ||
+                        s.to_lowercase() == "elipsoide"
+                ) => {
+                    match (shapes_table.get("rx").and_then(as_number), shapes_table.get("rz").and_then(as_number)) {
+                        (Some(rx), Some(rz)) => JoinShape::Ellipsoid(rx, rz),
+                        _ => {
+                            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Ellipsoid join without proper rx and rz. Using a line"));
+                            JoinShape::Line
+                        },
+                    }
+                },
+                Some(Value::String(s))
+                    if (s.to_lowercase() == "spline" ||
+                        s.to_lowercase() == "curva"
+                ) => {
+                    match shapes_table.get("radius").and_then(as_number) {
+                        Some(radius) => JoinShape::Spline(radius),
+                        None => {
+                            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Spline join without radius. Using a line"));
+                            JoinShape::Line
+                        }
+                    }
+                },
+                Some(Value::String(s))
+                    if (s.to_lowercase() == "prisma rectangular" ||
+                        s.to_lowercase() == "rectangular prism" ||
+                        s.to_lowercase() == "prisma" ||
+                        s.to_lowercase() == "prism" ||
+                        s.to_lowercase() == "paralelepipedo" ||
+                        s.to_lowercase() == "paralelepípedo" ||
+                        s.to_lowercase() == "parallelepiped"
+                ) => {
+                    let width = shapes_table.get("width").and_then(as_number);
+                    let height = shapes_table.get("height").and_then(as_number);
+                    
+                    let orientation_point = shapes_table.get("vector").and_then(|v| v.as_str());
+                    let orientation_vectors = orientation_point
+                        .and_then(|orientation_vectors| {
+                            config.get_vectors_for_point(orientation_vectors)
+                                .filter(|vectors| vectors.len() == 3)
+                                .map(|vectors| [
+                                    vectors[0].0.clone(),
+                                    vectors[1].0.clone(),
+                                    vectors[2].0.clone(),
+                                ])
+                        });
+                    
+                    let (Some(width), Some(height)) = (width, height) else {
+                        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Rectangular prism join without proper width or height. Using a line"));
+                        return JoinShape::Line;
+                    };
+
+                    if let (Some(orientation_point), None) = (orientation_point, &orientation_vectors) {
+                        errors.push(ConfigError::new(
+                            ConfigErrorKind::UnknownReference, 
+                            [path, vec!["vector".into()]].concat(), 
+                            format!("Orientation point {:?} not found in vectors (it needs 3 vectors)", orientation_point),
+                        ));
+                    }
+                    JoinShape::RectangularPrism(width, height, orientation_vectors)
+                },
+                _ => {
+                    errors.push(ConfigError::new(
+                        ConfigErrorKind::Unsupported, 
+                        [path, vec!["type".into()]].concat(), 
+                        format!("Shape {} not implemented. Using a line", shapes_table.get("type").map(|t| t.to_string()).unwrap_or_default()),
+                    ));
+                    JoinShape::Line
+                },
+            }
+        }
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::Unsupported, path, format!("Shape {} not implemented. Using a line", shape)));
+            JoinShape::Line
+        },
+    }
+}
 
-    Ok(config)
+fn generate_expanded_points(
+    point_groups: &Option<HashMap<String, Vec<String>>>, 
+    config: &mut Config, 
+    points: &[Value],
+    join_shape: JoinShape,
+    path: Vec<ConfigKey>,
+    errors: &mut Vec<ConfigError>,
+) {
+    let previous_errors = errors.len();
+    let expanded_points = expand_points(point_groups, points, path.clone(), errors);
+    if expanded_points.len() > 1 {
+        config.joins.get_or_insert_with(Vec::new).push((expanded_points, join_shape));
+    } else if errors.len() == previous_errors {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "A join needs at least 2 points"));
+    }
 }
 
-fn generate_expanded_points(point_groups: &Option<HashMap<String, Vec<String>>>, config: &mut Config, points: &Vec<Value>, join_shape: JoinShape) {
+/// Points of a join or a surface, with the references to point groups replaced by the points of the group
+fn expand_points(
+    point_groups: &Option<HashMap<String, Vec<String>>>, 
+    points: &[Value],
+    path: Vec<ConfigKey>,
+    errors: &mut Vec<ConfigError>,
+) -> Vec<String> {
     let mut expanded_points = Vec::new();
-    for point in points {
+    for (i, point) in points.iter().enumerate() {
+        let point_path = [path.clone(), vec![i.into()]].concat();
         match point {
             Value::String(point_name) => expanded_points.push(point_name.clone()),
-            Value::Array(group_ref) if (group_ref.len() == 1 && point_groups.is_some()) => {
-                expand_point_group(point_groups, &mut expanded_points, group_ref);
+            Value::Array(group_ref) if group_ref.len() == 1 => {
+                if let Some(points) = expand_point_group(point_groups, group_ref, point_path, errors) {
+                    expanded_points.extend(points);
+                }
             },
-            Value::Array(group_ref) => println!("Detected unexpected group reference: {:?}. Length of group reference: {}. point_groups: {:?}", group_ref, group_ref.len(), point_groups),
-            _ => {
-                println!("Invalid point in joins: {:?}", point);
-                continue
-            },                        
+            Value::Array(group_ref) => errors.push(ConfigError::new(
+                ConfigErrorKind::InvalidValue, 
+                point_path, 
+                format!("A group reference must contain only the name of the group, found {} elements", group_ref.len()),
+            )),
+            _ => errors.push(ConfigError::new(
+                ConfigErrorKind::InvalidValue, 
+                point_path, 
+                format!("Expected a point label or a group reference, found {}", point.type_str()),
+            )),
         }
     }
-    if expanded_points.len() > 1 {
-        if config.joins.is_none() {
-            config.joins = Some(vec![(expanded_points, join_shape)]);
-        } else {
-            config.joins.as_mut().unwrap().push((expanded_points, join_shape));
+    expanded_points
+}
+
+fn expand_point_group(
+    point_groups: &Option<HashMap<String, Vec<String>>>, 
+    group_ref: &[Value],
+    path: Vec<ConfigKey>,
+    errors: &mut Vec<ConfigError>,
+) -> Option<Vec<String>> {
+    let Some(Value::String(group_name)) = group_ref.first() else {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "A group reference must contain the name of the group"));
+        return None;
+    };
+    match point_groups.as_ref().and_then(|groups| groups.get(group_name)) {
+        Some(points) => Some(points.clone()),
+        None => {
+            errors.push(ConfigError::new(ConfigErrorKind::UnknownReference, path, format!("Point group {:?} is not defined in point_groups", group_name)));
+            None
         }
     }
 }
 
-fn expand_point_group(point_groups: &Option<HashMap<String, Vec<String>>>, expanded_points: &mut Vec<String>, group_ref: &Vec<Value>) {
-    if let Some(Value::String(group_name)) = group_ref.get(0) {
-        if let Some(points) = point_groups.as_ref().unwrap().get(group_name) {
-            expanded_points.extend(points.clone());
+/// Checks that a point of visible_points or of a point group is a valid regex (see point_regex). If it is not, the error
+/// is reported and the point must be ignored.
+fn check_point_regex(point: &str, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> bool {
+    match point_regex(point) {
+        Ok(_) => true,
+        Err(e) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("{:?} is not a valid regex. Ignoring it: {}", point, e)));
+            false
         }
     }
 }
 
-
-fn parse_point_group_config(table: Map<String, Value>) -> Result<PointGroupConfig, String> {
+fn parse_point_group_config(group_name: &str, table: &Map<String, Value>, errors: &mut Vec<ConfigError>) -> PointGroupConfig {
     let mut group_config = PointGroupConfig::default();
+    let path = |key: &str| vec![group_name.into(), "config".into(), key.into()];
 
-    group_config.point_color = table.get("point_color").and_then(|v| v.as_array()).and_then(|v| {
-        if v.len() == 3 {
-            Some(vec![v[0].as_integer().unwrap() as u8, v[1].as_integer().unwrap() as u8, v[2].as_integer().unwrap() as u8])
-        } else if v.len() == 4 {
-            Some(vec![v[0].as_integer().unwrap() as u8, v[1].as_integer().unwrap() as u8, v[2].as_integer().unwrap() as u8, v[3].as_integer().unwrap() as u8])
-        } else {
-            None
+    group_config.point_color = table.get("point_color").and_then(|v| parse_color(v, path("point_color"), errors));
+    group_config.point_size = table.get("point_size").and_then(|v| parse_float(v, path("point_size"), errors));
+    group_config.join_color = table.get("join_color").and_then(|v| parse_color(v, path("join_color"), errors));
+    group_config.line_thickness = table.get("line_thickness").and_then(|v| parse_float(v, path("line_thickness"), errors));
+    group_config.priority = table.get("priority").and_then(|v| {
+        let priority = v.as_integer();
+        if priority.is_none() {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("priority"), format!("Expected an integer, found {}", v.type_str())));
         }
+        priority
     });
-    group_config.point_size = table.get("point_size").and_then(|v| v.as_float());
-    group_config.join_color = table.get("join_color").and_then(|v| v.as_array()).and_then(|v| {
-        if v.len() == 3 {
-            Some(vec![v[0].as_integer().unwrap_or(27) as u8, v[1].as_integer().unwrap_or(210) as u8, v[2].as_integer().unwrap_or(27) as u8])
-        } else if v.len() == 4 {
-            Some(vec![v[0].as_integer().unwrap_or(27) as u8, v[1].as_integer().unwrap_or(210) as u8, v[2].as_integer().unwrap_or(27) as u8, v[3].as_integer().unwrap_or(50) as u8])
-        } else {
-            None
-        }
-    });
-    group_config.line_thickness = table.get("line_thickness").and_then(|v| v.as_float());
-    Ok(group_config)
-}
\ No newline at end of file
+    group_config
+}
+
+pub(crate) fn parse_float(value: &Value, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<f64> {
+    let number = as_number(value);
+    if number.is_none() {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected a number, found {}", value.type_str())));
+    }
+    number
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(f) => Some(*f),
+        Value::Integer(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_groups_too_big_to_match_are_reported() {
+        // Each regex is valid, but both together are over the size limit
+        let points = ["\"[a-z]{100000}\""; 2].join(", ");
+        let config_file = parse_config(&format!("[point_groups]\nbig = [{}]\nsmall = [\"LKNE\"]\n", points), false).unwrap();
+
+        assert_eq!(config_file.get_diagnostics().len(), 1);
+        assert_eq!(config_file.get_diagnostics()[0].key_path, vec!["point_groups".into(), "big".into()]);
+        assert_eq!(config_file.get_point_groups_containing("LKNE"), vec!["small"]);
+    }
+
+    #[test]
+    fn extends_can_be_chained() {
+        let config_file = parse_config(r#"
+            [base]
+            visible_points = ["LKNE", "RKNE"]
+            joins = [["LKNE", "RKNE"]]
+            point_color = "red"
+            line_thickness = 2.0
+            title = "Base"
+            [middle]
+            extends = "base"
+            visible_points = ["LANK", "LKNE"]
+            point_color = "blue"
+            [child]
+            extends = "middle"
+            joins = [["LKNE", "LANK"]]
+            point_size = 3.0
+        "#, false).unwrap();
+        let child = config_file.get_config("child").unwrap();
+
+        assert_eq!(config_file.get_diagnostics(), &Vec::new());
+        assert_eq!(child.get_visible_points(), Some(&vec!["LKNE".to_string(), "RKNE".to_string(), "LANK".to_string()]));
+        assert_eq!(child.get_joins().unwrap().iter().map(|(points, _)| points.join("-")).collect::<Vec<_>>(), vec!["LKNE-RKNE", "LKNE-LANK"]);
+        assert_eq!(config_file.get_point_color("LKNE", "child"), Some(ConfigColor::rgb(0, 0, 255)));
+        assert_eq!(child.line_thickness, Some(2.0));
+        assert_eq!(child.point_size, Some(3.0));
+        assert_eq!(child.get_metadata().title, None);
+    }
+
+    #[test]
+    fn extends_cycles_and_unknown_parents_are_ignored() {
+        let config_file = parse_config(r#"
+            [a]
+            extends = "b"
+            point_size = 1.0
+            [b]
+            extends = "a"
+            [itself]
+            extends = "itself"
+            [orphan]
+            extends = "missing"
+            point_size = 2.0
+        "#, false).unwrap();
+        let diagnostics = config_file.get_diagnostics();
+
+        assert_eq!(diagnostics.iter().map(|error| (error.kind, error.key_path[0].clone())).collect::<Vec<_>>(), vec![
+            (ConfigErrorKind::InvalidValue, "a".into()),
+            (ConfigErrorKind::InvalidValue, "b".into()),
+            (ConfigErrorKind::InvalidValue, "itself".into()),
+            (ConfigErrorKind::UnknownReference, "orphan".into()),
+        ]);
+        assert!(diagnostics[0].message.contains("a -> b -> a"), "{}", diagnostics[0].message);
+        assert!(diagnostics[2].message.contains("itself -> itself"), "{}", diagnostics[2].message);
+        // The configurations are kept, without the values of the parent
+        assert_eq!(config_file.get_config("b").unwrap().point_size, None);
+        assert_eq!(config_file.get_config("orphan").unwrap().point_size, Some(2.0));
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+
+use crate::c3d_config_color_map::ColorMapValue;
+use crate::c3d_config_toml::{point_regex, Config, ConfigFile};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssueKind {
+    /// A point that is a plain label, but there is no marker with that label
+    UnknownLabel(String),
+    /// A point that is a regex, but it does not match any label
+    UnmatchedRegex(String),
+    /// A point that is not a valid regex. Contains the point and the error of the regex
+    InvalidRegex(String, String),
+    /// A segment of a join that will never be represented, because at least one of its points is not a label.
+    /// Joins need the exact labels of the points, regex are not allowed.
+    UnrenderableJoin(String, String),
+    /// A vector that will never be represented, because its point or its vector is not a label
+    UnrenderableVector(String, String),
+}
+
+/// Problem found when checking a configuration against the labels of a C3D file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// Where the problem is, as `config1.joins` or `point_groups.hands`
+    pub context: String,
+    pub kind: ValidationIssueKind,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.context)?;
+        match &self.kind {
+            ValidationIssueKind::UnknownLabel(label) => write!(f, "label {:?} not found in the C3D", label),
+            ValidationIssueKind::UnmatchedRegex(regex) => write!(f, "regex {:?} does not match any label", regex),
+            ValidationIssueKind::InvalidRegex(regex, error) => write!(f, "{:?} is not a valid regex: {}", regex, error),
+            ValidationIssueKind::UnrenderableJoin(p1, p2) => write!(f, "join {} - {} will never be represented", p1, p2),
+            ValidationIssueKind::UnrenderableVector(point, vector) => write!(f, "vector {} at {} will never be represented", vector, point),
+        }
+    }
+}
+
+/// Result of ConfigFile::validate. The issues of the point groups come first, then the ones of the virtual points, and then
+/// the ones of each configuration, by name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Issues of a configuration (`config_name.*`)
+    pub fn issues_in_config(&self, config_name: &str) -> Vec<&ValidationIssue> {
+        let prefix = format!("{}.", config_name);
+        self.issues.iter().filter(|issue| issue.context.starts_with(&prefix)).collect()
+    }
+}
+
+impl ConfigFile {
+    /// Checks the point groups, the virtual points and every configuration against the labels of a C3D file.
+    /// Reports the labels that do not exist, the regex that match nothing, and the joins and vectors that can never be represented.
+    pub fn validate(&self, labels: &[String]) -> ValidationReport {
+        let mut labels = self.labels_with_canonical_names(labels);
+        let mut issues = Vec::new();
+
+        // Virtual points can use the labels and the virtual points defined before them
+        let mut virtual_point_issues = Vec::new();
+        for virtual_point in self.get_virtual_points() {
+            let context = format!("virtual_points.{}", virtual_point.name);
+            for point in &virtual_point.points {
+                let issue = ValidationIssue { context: context.clone(), kind: ValidationIssueKind::UnknownLabel(point.clone()) };
+                if !labels.contains(point) && !virtual_point_issues.contains(&issue) {
+                    virtual_point_issues.push(issue);
+                }
+            }
+            labels.push(virtual_point.name.clone());
+        }
+        let labels = &labels;
+
+        let mut group_names = self.get_point_groups().map(|g| g.keys().cloned().collect::<Vec<_>>()).unwrap_or_default();
+        group_names.sort();
+        for group_name in group_names {
+            if let Some(points) = self.get_point_group(&group_name) {
+                issues.extend(validate_points(&format!("point_groups.{}", group_name), points, labels));
+            }
+        }
+        issues.extend(virtual_point_issues);
+
+        for config_name in self.get_all_config_names() {
+            if let Some(config) = self.get_config(&config_name) {
+                issues.extend(validate_config(&config_name, config, labels));
+            }
+        }
+        ValidationReport { issues }
+    }
+
+    /// Checks a single configuration against the labels of a C3D file. See validate.
+    pub fn validate_config(&self, config_name: &str, labels: &[String]) -> ValidationReport {
+        let mut labels = self.labels_with_canonical_names(labels);
+        labels.extend(self.get_virtual_points().iter().map(|virtual_point| virtual_point.name.clone()));
+        let labels = &labels;
+        ValidationReport {
+            issues: self.get_config(config_name).map(|config| validate_config(config_name, config, labels)).unwrap_or_default(),
+        }
+    }
+
+    /// The configuration can name the points by their label or by the canonical name of an alias
+    fn labels_with_canonical_names(&self, labels: &[String]) -> Vec<String> {
+        let mut names = labels.to_vec();
+        for label in labels {
+            if let Some(canonical_name) = self.get_canonical_name(label) {
+                if !names.contains(canonical_name) {
+                    names.push(canonical_name.clone());
+                }
+            }
+        }
+        names
+    }
+}
+
+fn validate_config(config_name: &str, config: &Config, labels: &[String]) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    if let Some(points) = config.get_visible_points() {
+        issues.extend(validate_points(&format!("{}.visible_points", config_name), points, labels));
+    }
+
+    let context = format!("{}.joins", config_name);
+    for (join, _) in config.get_joins().into_iter().flatten() {
+        for pair in join.windows(2) {
+            let issue = ValidationIssue {
+                context: context.clone(),
+                kind: ValidationIssueKind::UnrenderableJoin(pair[0].clone(), pair[1].clone()),
+            };
+            if (!labels.contains(&pair[0]) || !labels.contains(&pair[1])) && !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+    }
+
+    let context = format!("{}.surfaces", config_name);
+    for surface in config.get_surfaces().into_iter().flatten() {
+        for point in &surface.points {
+            let issue = ValidationIssue { context: context.clone(), kind: ValidationIssueKind::UnknownLabel(point.clone()) };
+            if !labels.contains(point) && !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+    }
+
+    if let Some(ColorMapValue::Distance(reference)) = config.get_point_color_map().map(|color_map| &color_map.value) {
+        if !labels.contains(reference) {
+            issues.push(ValidationIssue {
+                context: format!("{}.point_color_map", config_name),
+                kind: ValidationIssueKind::UnknownLabel(reference.clone()),
+            });
+        }
+    }
+
+    let context = format!("{}.vectors", config_name);
+    let mut vectors = config.get_vectors().into_iter().flatten().collect::<Vec<_>>();
+    vectors.sort_by(|a, b| a.0.cmp(b.0));
+    for (point, point_vectors) in vectors {
+        for (vector, _) in point_vectors {
+            if !labels.contains(point) || !labels.contains(vector) {
+                issues.push(ValidationIssue {
+                    context: context.clone(),
+                    kind: ValidationIssueKind::UnrenderableVector(point.clone(), vector.clone()),
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+fn validate_points(context: &str, points: &[String], labels: &[String]) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = Vec::new();
+    for point in points {
+        let kind = match point_regex(point) {
+            Ok(re) if labels.iter().any(|label| re.is_match(label)) => continue,
+            Ok(_) if !point.starts_with('_') && regex::escape(point) == *point => ValidationIssueKind::UnknownLabel(point.clone()),
+            Ok(_) => ValidationIssueKind::UnmatchedRegex(point.clone()),
+            Err(e) => ValidationIssueKind::InvalidRegex(point.clone(), e.to_string()),
+        };
+        let issue = ValidationIssue { context: context.to_string(), kind };
+        if !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    }
+    issues
+}
@@ -0,0 +1,406 @@
+use serde::Deserialize;
+use toml::{Value, map::Map};
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::{parse_float, ConfigFile};
+
+/// Point that is not in the C3D, computed from other points in every frame. It is represented as any other marker.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VirtualPoint {
+    pub name: String,
+    /// Points it is computed from. Can be labels, canonical names of aliases or previous virtual points
+    pub points: Vec<String>,
+    pub kind: VirtualPointKind,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum VirtualPointKind {
+    Midpoint,                       // Between points[0] and points[1]
+    Centroid(Option<Vec<f64>>),     // Weights, one per point. All the points weigh the same if None
+    OffsetDistance(f64),            // From points[0] towards points[1], in millimetres
+    OffsetRatio(f64),               // From points[0] towards points[1], as a fraction of the segment (0.5 is the midpoint)
+    Projection,                     // Of points[0] onto the plane of points[1], points[2] and points[3]
+}
+
+impl VirtualPoint {
+    /// Position of the virtual point in a frame, from the positions of its points (in the order of `points`).
+    /// A point at the origin is a missing marker, as in the C3D, so the result is the origin if any point is missing
+    /// or the computation is not defined (a segment or a plane with no length).
+    pub fn compute(&self, positions: &[[f32; 3]]) -> [f32; 3] {
+        if positions.len() != self.points.len() || positions.contains(&[0.0; 3]) {
+            return [0.0; 3];
+        }
+        let result = match &self.kind {
+            VirtualPointKind::Midpoint => Some(lerp(positions[0], positions[1], 0.5)),
+            VirtualPointKind::Centroid(weights) => {
+                let weights = weights.clone().unwrap_or_else(|| vec![1.0; positions.len()]);
+                let total = weights.iter().sum::<f64>() as f32;
+                (total != 0.0).then(|| {
+                    let mut centroid = [0.0; 3];
+                    for (position, weight) in positions.iter().zip(&weights) {
+                        centroid = add(centroid, scale(*position, *weight as f32 / total));
+                    }
+                    centroid
+                })
+            }
+            VirtualPointKind::OffsetDistance(distance) => {
+                let segment = sub(positions[1], positions[0]);
+                let length = norm(segment);
+                (length != 0.0).then(|| add(positions[0], scale(segment, *distance as f32 / length)))
+            }
+            VirtualPointKind::OffsetRatio(ratio) => Some(lerp(positions[0], positions[1], *ratio as f32)),
+            VirtualPointKind::Projection => {
+                let normal = cross(sub(positions[2], positions[1]), sub(positions[3], positions[1]));
+                let length = norm(normal);
+                (length != 0.0).then(|| {
+                    let normal = scale(normal, 1.0 / length);
+                    sub(positions[0], scale(normal, dot(sub(positions[0], positions[1]), normal)))
+                })
+            }
+        };
+        result.unwrap_or([0.0; 3])
+    }
+}
+
+impl ConfigFile {
+    /// Virtual points, in the order of the file. A virtual point can use the ones defined before it.
+    pub fn get_virtual_points(&self) -> &Vec<VirtualPoint> {
+        &self.virtual_points
+    }
+
+    pub fn get_virtual_point(&self, name: &str) -> Option<&VirtualPoint> {
+        self.virtual_points.iter().find(|point| point.name == name)
+    }
+
+    /// Adds a virtual point. If there is one with the same name, it is replaced.
+    pub fn add_virtual_point(&mut self, virtual_point: VirtualPoint) {
+        match self.virtual_points.iter_mut().find(|point| point.name == virtual_point.name) {
+            Some(point) => *point = virtual_point,
+            None => self.virtual_points.push(virtual_point),
+        }
+    }
+
+    /// Computes the trajectories of the virtual points for every frame.
+    ///
+    /// `labels` are the labels of the C3D, and `position(frame, index)` the position of the label with that index.
+    /// The points of a virtual point are resolved through the aliases (see resolve_label). The virtual points that use a
+    /// point that is not in the C3D are skipped, and reported in `errors`. The result has the name and the trajectory of each
+    /// computed virtual point, in the order of the file.
+    pub fn compute_virtual_points(
+        &self,
+        labels: &[String],
+        num_frames: usize,
+        position: impl Fn(usize, usize) -> [f32; 3],
+        errors: &mut Vec<ConfigError>,
+    ) -> Vec<(String, Vec<[f32; 3]>)> {
+        let mut trajectories: Vec<(String, Vec<[f32; 3]>)> = Vec::new();
+        for virtual_point in &self.virtual_points {
+            // Source of each point: the index of a label, or a virtual point already computed
+            let sources = virtual_point.points.iter().map(|point| {
+                let label = self.resolve_label(point, labels);
+                match labels.iter().position(|l| *l == label) {
+                    Some(index) => Some(Ok(index)),
+                    None => trajectories.iter().position(|(name, _)| name == point).map(Err),
+                }
+            }).collect::<Option<Vec<_>>>();
+            let Some(sources) = sources else {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::UnknownReference,
+                    vec!["virtual_points".into(), (&virtual_point.name).into(), "points".into()],
+                    "The virtual point uses points that are not in the C3D, it is not computed",
+                ));
+                continue;
+            };
+            let trajectory = (0..num_frames).map(|frame| {
+                let positions = sources.iter().map(|source| match source {
+                    Ok(index) => position(frame, *index),
+                    Err(computed) => trajectories[*computed].1[frame],
+                }).collect::<Vec<_>>();
+                virtual_point.compute(&positions)
+            }).collect();
+            trajectories.push((virtual_point.name.clone(), trajectory));
+        }
+        trajectories
+    }
+}
+
+/// Parses the `[virtual_points]` table. Each virtual point is a table with its `type` and its points:
+/// - `midpoint`: `points = [a, b]`
+/// - `centroid`: `points = [a, b, ...]` and, optionally, `weights` (one per point)
+/// - `offset`: `points = [from, to]` and either `distance` (in millimetres) or `ratio` (fraction of the segment)
+/// - `projection`: `point` and `plane = [a, b, c]`
+pub(crate) fn parse_virtual_points(table: &Map<String, Value>, errors: &mut Vec<ConfigError>) -> Vec<VirtualPoint> {
+    let mut virtual_points = Vec::new();
+    for (name, value) in table {
+        let path = |key: &str| vec!["virtual_points".into(), name.into(), key.into()];
+        let Value::Table(definition) = value else {
+            errors.push(ConfigError::new(
+                ConfigErrorKind::InvalidValue,
+                vec!["virtual_points".into(), name.into()],
+                format!("Expected a table with the type and the points of the virtual point, found {}", value.type_str()),
+            ));
+            continue;
+        };
+        let point_type = match definition.get("type") {
+            Some(Value::String(point_type)) => point_type.as_str(),
+            Some(other) => {
+                errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("type"), format!("Expected a string, found {}", other.type_str())));
+                continue;
+            }
+            None => {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::InvalidValue,
+                    vec!["virtual_points".into(), name.into()],
+                    "A virtual point needs a type (midpoint, centroid, offset or projection)",
+                ));
+                continue;
+            }
+        };
+
+        let parsed = match point_type {
+            "midpoint" => parse_points(definition, "points", Some(2), path("points"), errors)
+                .map(|points| (points, VirtualPointKind::Midpoint)),
+            "centroid" => parse_points(definition, "points", None, path("points"), errors).and_then(|points| {
+                let weights = match definition.get("weights") {
+                    Some(Value::Array(weights)) if weights.len() == points.len() => {
+                        let weights = weights.iter().enumerate()
+                            .map(|(i, weight)| parse_float(weight, [path("weights"), vec![i.into()]].concat(), errors))
+                            .collect::<Option<Vec<_>>>()?;
+                        Some(weights)
+                    }
+                    Some(Value::Array(weights)) => {
+                        errors.push(ConfigError::new(
+                            ConfigErrorKind::InvalidValue,
+                            path("weights"),
+                            format!("Expected one weight per point ({}), found {}", points.len(), weights.len()),
+                        ));
+                        return None;
+                    }
+                    Some(other) => {
+                        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path("weights"), format!("Expected an array of numbers, found {}", other.type_str())));
+                        return None;
+                    }
+                    None => None,
+                };
+                Some((points, VirtualPointKind::Centroid(weights)))
+            }),
+            "offset" => parse_points(definition, "points", Some(2), path("points"), errors).and_then(|points| {
+                match (definition.get("distance"), definition.get("ratio")) {
+                    (Some(distance), None) => parse_float(distance, path("distance"), errors)
+                        .map(|distance| (points, VirtualPointKind::OffsetDistance(distance))),
+                    (None, Some(ratio)) => parse_float(ratio, path("ratio"), errors)
+                        .map(|ratio| (points, VirtualPointKind::OffsetRatio(ratio))),
+                    _ => {
+                        errors.push(ConfigError::new(
+                            ConfigErrorKind::InvalidValue,
+                            vec!["virtual_points".into(), name.into()],
+                            "An offset needs either a distance or a ratio",
+                        ));
+                        None
+                    }
+                }
+            }),
+            "projection" => {
+                let point = parse_points(definition, "point", Some(1), path("point"), errors);
+                let plane = parse_points(definition, "plane", Some(3), path("plane"), errors);
+                point.zip(plane).map(|(point, plane)| ([point, plane].concat(), VirtualPointKind::Projection))
+            }
+            _ => {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::Unsupported,
+                    path("type"),
+                    format!("Unknown virtual point type {:?}, expected midpoint, centroid, offset or projection. Ignoring it", point_type),
+                ));
+                None
+            }
+        };
+
+        if let Some((points, kind)) = parsed {
+            virtual_points.push(VirtualPoint { name: name.clone(), points, kind });
+        }
+    }
+    virtual_points
+}
+
+/// Parses the points of a virtual point: a label, or an array of labels. If `count` is given, there must be exactly that many.
+fn parse_points(definition: &Map<String, Value>, key: &str, count: Option<usize>, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<Vec<String>> {
+    let points = match definition.get(key) {
+        Some(Value::String(point)) => vec![point.clone()],
+        Some(Value::Array(points)) => {
+            let mut labels = Vec::new();
+            for (i, point) in points.iter().enumerate() {
+                match point {
+                    Value::String(point) => labels.push(point.clone()),
+                    _ => {
+                        errors.push(ConfigError::new(
+                            ConfigErrorKind::InvalidValue,
+                            [path.clone(), vec![i.into()]].concat(),
+                            format!("Expected a point label, found {}", point.type_str()),
+                        ));
+                        return None;
+                    }
+                }
+            }
+            labels
+        }
+        Some(other) => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected a point or an array of points, found {}", other.type_str())));
+            return None;
+        }
+        None => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Missing the field {:?}", key)));
+            return None;
+        }
+    };
+    match count {
+        Some(count) if points.len() != count => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Expected {} points, found {}", count, points.len())));
+            None
+        }
+        None if points.is_empty() => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Expected at least one point"));
+            None
+        }
+        _ => Some(points),
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn norm(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    add(a, scale(sub(b, a), t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn virtual_point(points: &[&str], kind: VirtualPointKind) -> VirtualPoint {
+        VirtualPoint { name: "V".to_string(), points: points.iter().map(|p| p.to_string()).collect(), kind }
+    }
+
+    fn assert_close(result: [f32; 3], expected: [f32; 3]) {
+        assert!(norm(sub(result, expected)) < 1e-5, "{:?} != {:?}", result, expected);
+    }
+
+    #[test]
+    fn midpoint() {
+        let point = virtual_point(&["A", "B"], VirtualPointKind::Midpoint);
+        assert_close(point.compute(&[[1.0, 2.0, 3.0], [3.0, 6.0, -1.0]]), [2.0, 4.0, 1.0]);
+    }
+
+    #[test]
+    fn centroid() {
+        let point = virtual_point(&["A", "B", "C"], VirtualPointKind::Centroid(None));
+        assert_close(point.compute(&[[3.0, 0.0, 1.0], [0.0, 3.0, 1.0], [0.0, 0.0, 1.0]]), [1.0, 1.0, 1.0]);
+
+        let point = virtual_point(&["A", "B"], VirtualPointKind::Centroid(Some(vec![3.0, 1.0])));
+        assert_close(point.compute(&[[4.0, 4.0, 4.0], [8.0, 4.0, 4.0]]), [5.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    fn centroid_with_zero_total_weight_is_missing() {
+        let point = virtual_point(&["A", "B"], VirtualPointKind::Centroid(Some(vec![1.0, -1.0])));
+        assert_eq!(point.compute(&[[1.0, 1.0, 1.0], [2.0, 2.0, 2.0]]), [0.0; 3]);
+    }
+
+    #[test]
+    fn offset_distance() {
+        let point = virtual_point(&["A", "B"], VirtualPointKind::OffsetDistance(30.0));
+        assert_close(point.compute(&[[10.0, 10.0, 10.0], [10.0, 110.0, 10.0]]), [10.0, 40.0, 10.0]);
+
+        // Negative distances go the other way
+        let point = virtual_point(&["A", "B"], VirtualPointKind::OffsetDistance(-5.0));
+        assert_close(point.compute(&[[10.0, 10.0, 10.0], [10.0, 110.0, 10.0]]), [10.0, 5.0, 10.0]);
+    }
+
+    #[test]
+    fn offset_distance_on_a_segment_with_no_length_is_missing() {
+        let point = virtual_point(&["A", "B"], VirtualPointKind::OffsetDistance(30.0));
+        assert_eq!(point.compute(&[[1.0, 2.0, 3.0], [1.0, 2.0, 3.0]]), [0.0; 3]);
+    }
+
+    #[test]
+    fn offset_ratio() {
+        let point = virtual_point(&["A", "B"], VirtualPointKind::OffsetRatio(0.25));
+        assert_close(point.compute(&[[0.0, 0.0, 4.0], [8.0, 0.0, 4.0]]), [2.0, 0.0, 4.0]);
+
+        let point = virtual_point(&["A", "B"], VirtualPointKind::OffsetRatio(1.5));
+        assert_close(point.compute(&[[0.0, 0.0, 4.0], [8.0, 0.0, 4.0]]), [12.0, 0.0, 4.0]);
+    }
+
+    #[test]
+    fn projection() {
+        let point = virtual_point(&["P", "A", "B", "C"], VirtualPointKind::Projection);
+        let plane = [[1.0, 1.0, 10.0], [5.0, 1.0, 10.0], [1.0, 5.0, 10.0]];
+        assert_close(point.compute(&[[3.0, 7.0, 25.0], plane[0], plane[1], plane[2]]), [3.0, 7.0, 10.0]);
+    }
+
+    #[test]
+    fn projection_on_a_plane_with_no_area_is_missing() {
+        let point = virtual_point(&["P", "A", "B", "C"], VirtualPointKind::Projection);
+        let collinear = [[1.0, 1.0, 1.0], [2.0, 2.0, 2.0], [3.0, 3.0, 3.0]];
+        assert_eq!(point.compute(&[[3.0, 7.0, 25.0], collinear[0], collinear[1], collinear[2]]), [0.0; 3]);
+    }
+
+    #[test]
+    fn missing_marker_gives_a_missing_point() {
+        let kinds = [
+            (vec!["A", "B"], VirtualPointKind::Midpoint),
+            (vec!["A", "B"], VirtualPointKind::Centroid(None)),
+            (vec!["A", "B"], VirtualPointKind::OffsetDistance(10.0)),
+            (vec!["A", "B"], VirtualPointKind::OffsetRatio(0.5)),
+        ];
+        for (points, kind) in kinds {
+            let point = virtual_point(&points, kind);
+            assert_eq!(point.compute(&[[1.0, 2.0, 3.0], [0.0; 3]]), [0.0; 3]);
+        }
+        let point = virtual_point(&["P", "A", "B", "C"], VirtualPointKind::Projection);
+        assert_eq!(point.compute(&[[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]]), [0.0; 3]);
+
+        // Also when the number of positions is not the number of points
+        let point = virtual_point(&["A", "B"], VirtualPointKind::Midpoint);
+        assert_eq!(point.compute(&[[1.0, 2.0, 3.0]]), [0.0; 3]);
+    }
+
+    #[test]
+    fn virtual_points_with_unknown_points_are_reported() {
+        let mut config_file = ConfigFile::default();
+        config_file.add_virtual_point(virtual_point(&["A", "B"], VirtualPointKind::Midpoint));
+        config_file.add_virtual_point(VirtualPoint { name: "W".to_string(), points: vec!["A".to_string(), "X".to_string()], kind: VirtualPointKind::Midpoint });
+        config_file.add_virtual_point(VirtualPoint { name: "U".to_string(), points: vec!["V".to_string(), "B".to_string()], kind: VirtualPointKind::Midpoint });
+
+        let labels = ["A".to_string(), "B".to_string()];
+        let positions = [[2.0, 0.0, 0.0], [4.0, 0.0, 0.0]];
+        let mut errors = Vec::new();
+        let trajectories = config_file.compute_virtual_points(&labels, 1, |_, i| positions[i], &mut errors);
+
+        // U uses V, that is computed before it
+        assert_eq!(trajectories, vec![("V".to_string(), vec![[3.0, 0.0, 0.0]]), ("U".to_string(), vec![[3.5, 0.0, 0.0]])]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::UnknownReference);
+        assert_eq!(errors[0].key_path, vec!["virtual_points".into(), "W".into(), "points".into()]);
+    }
+}
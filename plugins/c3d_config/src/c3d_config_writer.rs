@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::fs;
+
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::c3d_config_axes::Handedness;
+use crate::c3d_config_camera::CameraView;
+use crate::c3d_config_color::ConfigColor;
+use crate::c3d_config_color_map::{ColorMap, ColorMapValue};
+use crate::c3d_config_defaults::Defaults;
+use crate::c3d_config_error::*;
+use crate::c3d_config_toml::{Config, ConfigFile, JoinShape, PointGroupConfig};
+use crate::c3d_config_virtual::{VirtualPoint, VirtualPointKind};
+
+impl ConfigFile {
+    /// Writes the configuration as TOML. parse_config reads the result back into an equal ConfigFile.
+    ///
+    /// Points of visible_points and joins that match a whole point group are written as a reference to the group (`["group"]`).
+    /// There are some cases that the TOML format cannot represent, and are not kept. They are returned as Unsupported errors,
+    /// with the key path of the value that is not written:
+    /// - A configuration with the same name as a point group with config (`[name]` and `[name.config]`).
+    /// - The orientation of a rectangular prism, if the configuration has no point with exactly those 3 vectors.
+    /// - A projection virtual point without points (only possible if the VirtualPoint is built in code).
+    ///
+    /// Configurations that extend another one are written with the inherited values, without `extends`.
+    pub fn to_toml(&self) -> (String, Vec<ConfigError>) {
+        let mut document = DocumentMut::new();
+        let mut errors = Vec::new();
+        let groups = self.point_groups.clone().unwrap_or_default();
+
+        // Point groups keep their order, as it sets their precedence
+        let group_names = self.point_groups_order.iter().filter(|name| groups.contains_key(*name)).cloned().collect::<Vec<_>>();
+        if !group_names.is_empty() {
+            let mut groups_table = Table::new();
+            for group_name in &group_names {
+                groups_table.insert(group_name, Item::Value(Value::Array(Array::from_iter(groups[group_name].iter()))));
+            }
+            document.insert("point_groups", Item::Table(groups_table));
+        }
+
+        if let Some(aliases) = &self.aliases {
+            let mut canonical_names = aliases.keys().collect::<Vec<_>>();
+            canonical_names.sort();
+            let mut aliases_table = Table::new();
+            for canonical_name in canonical_names {
+                aliases_table.insert(canonical_name, Item::Value(Value::Array(Array::from_iter(aliases[canonical_name].iter()))));
+            }
+            document.insert("aliases", Item::Table(aliases_table));
+        }
+
+        // Virtual points keep their order, as each one can use the ones before it
+        if !self.virtual_points.is_empty() {
+            let mut virtual_points_table = Table::new();
+            for virtual_point in &self.virtual_points {
+                if let Some(table) = virtual_point_to_inline_table(virtual_point, &mut errors) {
+                    virtual_points_table.insert(&virtual_point.name, Item::Value(Value::InlineTable(table)));
+                }
+            }
+            document.insert("virtual_points", Item::Table(virtual_points_table));
+        }
+
+        if let Some(views) = &self.views {
+            let mut view_names = views.keys().collect::<Vec<_>>();
+            view_names.sort();
+            let mut views_table = Table::new();
+            for view_name in view_names {
+                views_table.insert(view_name, Item::Value(Value::InlineTable(camera_to_inline_table(&views[view_name]))));
+            }
+            document.insert("views", Item::Table(views_table));
+        }
+
+        if self.defaults != Defaults::default() {
+            document.insert("defaults", Item::Table(defaults_to_table(&self.defaults)));
+        }
+
+        let mut group_configs = self.point_groups_config.iter().flatten().collect::<Vec<_>>();
+        group_configs.sort_by_key(|(group_name, _)| (group_names.iter().position(|name| name == *group_name), *group_name));
+        for (group_name, group_config) in group_configs {
+            let mut group_table = Table::new();
+            group_table.set_implicit(true);
+            group_table.insert("config", Item::Table(point_group_config_to_table(group_config)));
+            document.insert(group_name, Item::Table(group_table));
+        }
+
+        for config_name in self.get_all_config_names() {
+            if document.contains_key(&config_name) {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::Unsupported,
+                    vec![(&config_name).into()],
+                    "The configuration has the same name as a point group config, it cannot be written",
+                ));
+                continue;
+            }
+            let config = &self.config_name[&config_name];
+            document.insert(&config_name, Item::Table(config_to_table(&config_name, config, &groups, &group_names, &mut errors)));
+        }
+
+        (document.to_string(), errors)
+    }
+
+    /// Writes the configuration as TOML, without the values that cannot be written (see to_toml)
+    pub fn to_toml_string(&self) -> String {
+        self.to_toml().0
+    }
+
+    /// Writes the configuration to a TOML file. Returns the values that could not be written (see to_toml).
+    pub fn save_config(&self, path: &str) -> std::io::Result<Vec<ConfigError>> {
+        let (content, errors) = self.to_toml();
+        fs::write(path, content)?;
+        Ok(errors)
+    }
+}
+
+fn defaults_to_table(defaults: &Defaults) -> Table {
+    let mut table = Table::new();
+    if let Some(marker_radius) = defaults.marker_radius {
+        table.insert("marker_radius", toml_edit::value(marker_radius));
+    }
+    if let Some(point_size) = defaults.point_size {
+        table.insert("point_size", toml_edit::value(point_size));
+    }
+    if let Some(point_color) = &defaults.point_color {
+        table.insert("point_color", color_item(point_color));
+    }
+    if let Some(join_color) = &defaults.join_color {
+        table.insert("join_color", color_item(join_color));
+    }
+    if let Some(line_thickness) = defaults.line_thickness {
+        table.insert("line_thickness", toml_edit::value(line_thickness));
+    }
+    if let Some(vector_colors) = &defaults.vector_colors {
+        table.insert("vector_colors", Item::Value(Value::Array(Array::from_iter(vector_colors.iter().map(color_value)))));
+    }
+    if let Some(vector_color) = &defaults.vector_color {
+        table.insert("vector_color", color_item(vector_color));
+    }
+    if let Some(unit_scale) = defaults.unit_scale {
+        table.insert("unit_scale", toml_edit::value(unit_scale));
+    }
+    if let Some(up_axis) = defaults.up_axis {
+        table.insert("up_axis", toml_edit::value(up_axis.name()));
+    }
+    if let Some(forward_axis) = defaults.forward_axis {
+        table.insert("forward_axis", toml_edit::value(forward_axis.name()));
+    }
+    match defaults.handedness {
+        Some(Handedness::Right) => { table.insert("handedness", toml_edit::value("right")); }
+        Some(Handedness::Left) => { table.insert("handedness", toml_edit::value("left")); }
+        None => {}
+    }
+    table
+}
+
+fn point_group_config_to_table(group_config: &PointGroupConfig) -> Table {
+    let mut table = Table::new();
+    if let Some(point_color) = &group_config.point_color {
+        table.insert("point_color", color_item(point_color));
+    }
+    if let Some(point_size) = group_config.point_size {
+        table.insert("point_size", toml_edit::value(point_size));
+    }
+    if let Some(join_color) = &group_config.join_color {
+        table.insert("join_color", color_item(join_color));
+    }
+    if let Some(line_thickness) = group_config.line_thickness {
+        table.insert("line_thickness", toml_edit::value(line_thickness));
+    }
+    if let Some(priority) = group_config.priority {
+        table.insert("priority", toml_edit::value(priority));
+    }
+    table
+}
+
+fn config_to_table(name: &str, config: &Config, groups: &HashMap<String, Vec<String>>, group_names: &[String], errors: &mut Vec<ConfigError>) -> Table {
+    let mut table = Table::new();
+
+    let metadata = config.get_metadata();
+    if let Some(title) = &metadata.title {
+        table.insert("title", toml_edit::value(title.as_str()));
+    }
+    if let Some(description) = &metadata.description {
+        table.insert("description", toml_edit::value(description.as_str()));
+    }
+    if let Some(order) = metadata.order {
+        table.insert("order", toml_edit::value(order));
+    }
+    if let Some(hotkey) = metadata.hotkey {
+        table.insert("hotkey", toml_edit::value(hotkey as i64));
+    }
+
+    if let Some(visible_points) = config.visible_points.as_ref().filter(|p| !p.is_empty()) {
+        table.insert("visible_points", Item::Value(Value::Array(points_with_group_references(visible_points, groups, group_names))));
+    }
+
+    // Vectors go before joins, as rectangular prisms refer to them
+    if let Some(vectors) = &config.vectors {
+        let mut points = vectors.keys().collect::<Vec<_>>();
+        points.sort();
+        let mut vectors_array = Array::new();
+        for point in points {
+            for (vector, scale) in &vectors[point] {
+                vectors_array.push(Array::from_iter([Value::from(point.as_str()), Value::from(vector.as_str()), Value::from(*scale)]));
+            }
+        }
+        table.insert("vectors", Item::Value(Value::Array(multiline(vectors_array))));
+    }
+
+    if let Some(joins) = config.joins.as_ref().filter(|j| !j.is_empty()) {
+        let mut joins_array = Array::new();
+        for (i, (points, shape)) in joins.iter().enumerate() {
+            let points = points_with_group_references(points, groups, group_names);
+            let path = vec![name.into(), "joins".into(), i.into(), "shape".into()];
+            match shape_to_inline_table(shape, config, path, errors) {
+                Some(shape) => {
+                    let mut join = InlineTable::new();
+                    join.insert("points", Value::Array(points));
+                    join.insert("shape", Value::InlineTable(shape));
+                    joins_array.push(join);
+                }
+                None => joins_array.push(points),
+            }
+        }
+        table.insert("joins", Item::Value(Value::Array(multiline(joins_array))));
+    }
+
+    if let Some(surfaces) = config.surfaces.as_ref().filter(|s| !s.is_empty()) {
+        let mut surfaces_array = Array::new();
+        for surface in surfaces {
+            let points = points_with_group_references(&surface.points, groups, group_names);
+            match &surface.color {
+                Some(color) => {
+                    let mut surface_table = InlineTable::new();
+                    surface_table.insert("points", Value::Array(points));
+                    surface_table.insert("color", color_value(color));
+                    surfaces_array.push(surface_table);
+                }
+                None => surfaces_array.push(points),
+            }
+        }
+        table.insert("surfaces", Item::Value(Value::Array(multiline(surfaces_array))));
+    }
+
+    if let Some(point_color) = &config.point_color {
+        table.insert("point_color", color_item(point_color));
+    }
+    if let Some(join_color) = &config.join_color {
+        table.insert("join_color", color_item(join_color));
+    }
+    if let Some(line_thickness) = config.line_thickness {
+        table.insert("line_thickness", toml_edit::value(line_thickness));
+    }
+    if let Some(point_size) = config.point_size {
+        table.insert("point_size", toml_edit::value(point_size));
+    }
+    match &config.camera {
+        Some(CameraView { view: Some(view), center: None, distance: None, yaw: None, pitch: None }) => {
+            table.insert("camera", toml_edit::value(view.as_str()));
+        }
+        Some(camera) => {
+            table.insert("camera", Item::Value(Value::InlineTable(camera_to_inline_table(camera))));
+        }
+        None => {}
+    }
+    if let Some(required_labels) = &config.required_labels {
+        table.insert("required_labels", Item::Value(Value::Array(Array::from_iter(required_labels.iter()))));
+    }
+    if let Some(match_threshold) = config.match_threshold {
+        table.insert("match_threshold", toml_edit::value(match_threshold));
+    }
+    if let Some(color_map) = &config.point_color_map {
+        table.insert("point_color_map", Item::Value(Value::InlineTable(color_map_to_inline_table(color_map))));
+    }
+    if let Some(color_map) = &config.join_color_map {
+        table.insert("join_color_map", Item::Value(Value::InlineTable(color_map_to_inline_table(color_map))));
+    }
+    table
+}
+
+fn color_map_to_inline_table(color_map: &ColorMap) -> InlineTable {
+    let mut table = InlineTable::new();
+    match &color_map.value {
+        ColorMapValue::Speed => { table.insert("value", "speed".into()); }
+        ColorMapValue::Distance(reference) => {
+            table.insert("value", "distance".into());
+            table.insert("reference", reference.as_str().into());
+        }
+        ColorMapValue::Length => { table.insert("value", "length".into()); }
+        ColorMapValue::LengthChange => { table.insert("value", "length_change".into()); }
+    }
+    table.insert("palette", color_map.palette.name().into());
+    if let Some(range) = color_map.range {
+        table.insert("range", Value::Array(Array::from_iter(range)));
+    }
+    table
+}
+
+fn camera_to_inline_table(camera: &CameraView) -> InlineTable {
+    let mut table = InlineTable::new();
+    if let Some(view) = &camera.view {
+        table.insert("view", view.as_str().into());
+    }
+    if let Some(center) = camera.center {
+        table.insert("center", Value::Array(Array::from_iter(center)));
+    }
+    if let Some(distance) = camera.distance {
+        table.insert("distance", distance.into());
+    }
+    if let Some(yaw) = camera.yaw {
+        table.insert("yaw", yaw.into());
+    }
+    if let Some(pitch) = camera.pitch {
+        table.insert("pitch", pitch.into());
+    }
+    table
+}
+
+fn virtual_point_to_inline_table(virtual_point: &VirtualPoint, errors: &mut Vec<ConfigError>) -> Option<InlineTable> {
+    let mut table = InlineTable::new();
+    let points = Value::Array(Array::from_iter(virtual_point.points.iter()));
+    match &virtual_point.kind {
+        VirtualPointKind::Midpoint => {
+            table.insert("type", "midpoint".into());
+            table.insert("points", points);
+        }
+        VirtualPointKind::Centroid(weights) => {
+            table.insert("type", "centroid".into());
+            table.insert("points", points);
+            if let Some(weights) = weights {
+                table.insert("weights", Value::Array(Array::from_iter(weights.iter().copied())));
+            }
+        }
+        VirtualPointKind::OffsetDistance(distance) => {
+            table.insert("type", "offset".into());
+            table.insert("points", points);
+            table.insert("distance", (*distance).into());
+        }
+        VirtualPointKind::OffsetRatio(ratio) => {
+            table.insert("type", "offset".into());
+            table.insert("points", points);
+            table.insert("ratio", (*ratio).into());
+        }
+        VirtualPointKind::Projection => {
+            let Some((point, plane)) = virtual_point.points.split_first() else {
+                errors.push(ConfigError::new(
+                    ConfigErrorKind::Unsupported,
+                    vec!["virtual_points".into(), (&virtual_point.name).into(), "point".into()],
+                    "A projection needs the point to project. Not written",
+                ));
+                return None;
+            };
+            table.insert("type", "projection".into());
+            table.insert("point", point.as_str().into());
+            table.insert("plane", Value::Array(Array::from_iter(plane.iter())));
+        }
+    }
+    Some(table)
+}
+
+/// Lines are written as a plain array of points, so they have no shape table. `path` is the key path of the shape, for the
+/// errors.
+fn shape_to_inline_table(shape: &JoinShape, config: &Config, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<InlineTable> {
+    let mut table = InlineTable::new();
+    match shape {
+        JoinShape::Line => return None,
+        JoinShape::Cylinder(radius) => {
+            table.insert("type", "cylinder".into());
+            table.insert("radius", (*radius).into());
+        }
+        JoinShape::SemiCone(radius1, radius2) => {
+            table.insert("type", "semicone".into());
+            table.insert("radius1", (*radius1).into());
+            table.insert("radius2", (*radius2).into());
+        }
+        JoinShape::Capsule(radius) => {
+            table.insert("type", "capsule".into());
+            table.insert("radius", (*radius).into());
+        }
+        JoinShape::Ellipsoid(rx, rz) => {
+            table.insert("type", "ellipsoid".into());
+            table.insert("rx", (*rx).into());
+            table.insert("rz", (*rz).into());
+        }
+        JoinShape::Spline(radius) => {
+            table.insert("type", "spline".into());
+            table.insert("radius", (*radius).into());
+        }
+        JoinShape::RectangularPrism(width, height, orientation) => {
+            table.insert("type", "prism".into());
+            table.insert("width", (*width).into());
+            table.insert("height", (*height).into());
+            if let Some(orientation) = orientation {
+                let orientation_point = config.vectors.iter().flatten().find_map(|(point, vectors)| {
+                    let names = vectors.iter().map(|(name, _)| name).collect::<Vec<_>>();
+                    (names == orientation.iter().collect::<Vec<_>>()).then_some(point)
+                });
+                match orientation_point {
+                    Some(point) => { table.insert("vector", point.as_str().into()); }
+                    None => errors.push(ConfigError::new(
+                        ConfigErrorKind::Unsupported,
+                        [path, vec!["vector".into()]].concat(),
+                        format!("No point has the orientation vectors {:?}, the orientation cannot be written", orientation),
+                    )),
+                }
+            }
+        }
+    }
+    Some(table)
+}
+
+/// Replaces the runs of points that are a whole point group by a reference to the group. The longest group is preferred.
+fn points_with_group_references(points: &[String], groups: &HashMap<String, Vec<String>>, group_names: &[String]) -> Array {
+    let mut array = Array::new();
+    let mut i = 0;
+    while i < points.len() {
+        let group = group_names
+            .iter()
+            .filter(|name| !groups[*name].is_empty() && points[i..].starts_with(&groups[*name]))
+            .max_by_key(|name| groups[*name].len());
+        match group {
+            Some(group_name) => {
+                array.push(Array::from_iter([group_name.as_str()]));
+                i += groups[group_name].len();
+            }
+            None => {
+                array.push(points[i].as_str());
+                i += 1;
+            }
+        }
+    }
+    array
+}
+
+fn color_item(color: &ConfigColor) -> Item {
+    Item::Value(color_value(color))
+}
+
+fn color_value(color: &ConfigColor) -> Value {
+    Value::Array(Array::from_iter(color.components().into_iter().map(|c| c as i64)))
+}
+
+/// One element per line, as arrays of joins and vectors are written by hand
+fn multiline(mut array: Array) -> Array {
+    for value in array.iter_mut() {
+        value.decor_mut().set_prefix("\n    ");
+    }
+    array.set_trailing_comma(true);
+    array.set_trailing("\n");
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c3d_config_toml::{parse_config, PointGroupConfig};
+
+    #[test]
+    fn round_trip_of_the_example_config() {
+        let config_file = parse_config(include_str!("../../../assets/config_file.toml"), false).unwrap();
+        let (content, errors) = config_file.to_toml();
+        assert_eq!(errors, Vec::new());
+
+        let written = parse_config(&content, false).unwrap();
+        assert_eq!(written.get_diagnostics(), &Vec::new());
+        assert_eq!(written, config_file);
+    }
+
+    #[test]
+    fn config_with_the_name_of_a_point_group_config_is_reported() {
+        let mut config_file = ConfigFile::default();
+        config_file.add_point_group("arm".to_string(), vec!["ELB".to_string()]);
+        config_file.add_point_group_config("arm".to_string(), PointGroupConfig::default());
+        config_file.config_name.insert("arm".to_string(), Config::default());
+
+        let (_, errors) = config_file.to_toml();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::Unsupported);
+        assert_eq!(errors[0].key_path, vec![ConfigKey::from("arm")]);
+    }
+
+    #[test]
+    fn prism_orientation_without_point_is_reported() {
+        let mut config = Config::default();
+        let orientation = ["Iv".to_string(), "Jv".to_string(), "Kv".to_string()];
+        config.joins = Some(vec![
+            (vec!["A".to_string(), "B".to_string()], JoinShape::Line),
+            (vec!["B".to_string(), "C".to_string()], JoinShape::RectangularPrism(1.0, 2.0, Some(orientation))),
+        ]);
+        let mut config_file = ConfigFile::default();
+        config_file.config_name.insert("arms".to_string(), config);
+
+        let (content, errors) = config_file.to_toml();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::Unsupported);
+        assert_eq!(errors[0].key_path, vec!["arms".into(), "joins".into(), 1.into(), "shape".into(), "vector".into()]);
+
+        // The prism is written without its orientation
+        let written = parse_config(&content, false).unwrap();
+        let joins = written.get_config("arms").unwrap().get_joins().unwrap();
+        assert_eq!(joins[1].1, JoinShape::RectangularPrism(1.0, 2.0, None));
+    }
+
+    #[test]
+    fn projection_without_points_is_reported() {
+        let mut config_file = ConfigFile::default();
+        config_file.virtual_points = vec![
+            VirtualPoint { name: "Empty".to_string(), points: Vec::new(), kind: VirtualPointKind::Projection },
+            VirtualPoint { name: "Mid".to_string(), points: vec!["A".to_string(), "B".to_string()], kind: VirtualPointKind::Midpoint },
+        ];
+
+        let (content, errors) = config_file.to_toml();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConfigErrorKind::Unsupported);
+        assert_eq!(errors[0].key_path, vec!["virtual_points".into(), "Empty".into(), "point".into()]);
+        let written = parse_config(&content, false).unwrap();
+        assert_eq!(written.get_virtual_points().iter().map(|point| point.name.as_str()).collect::<Vec<_>>(), vec!["Mid"]);
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde_yaml::{Mapping, Value as YamlValue};
+use toml::{map::Map, Value};
+
+use crate::c3d_config_error::*;
+use crate::c3d_config_include::include_files;
+use crate::c3d_config_toml::{parse_config_table, ConfigFile};
+
+/// Fields of a point group that are styles (and its priority), and go to the `[group.config]` table in the TOML layout
+const GROUP_STYLE_KEYS: [&str; 5] = ["point_color", "point_size", "join_color", "line_thickness", "priority"];
+
+fn read_config_yaml(file_or_string: &str, from_file: bool) -> Result<YamlValue, ConfigError> {
+    let content = if from_file {
+        fs::read_to_string(file_or_string).map_err(|e| 
+            ConfigError::new(ConfigErrorKind::Io, Vec::new(), format!("Could not read {}: {}", file_or_string, e))
+        )?
+    } else {
+        file_or_string.to_string()
+    };
+    serde_yaml::from_str(&content).map_err(|e| {
+        let error = ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), e.to_string());
+        match e.location() {
+            Some(location) => error.with_line_column(location.line(), location.column()),
+            None => error,
+        }
+    })
+}
+
+/// Parses a YAML configuration. The document is translated to the layout of the TOML format, so the result is the same
+/// ConfigFile that parse_config would return for the equivalent TOML file.
+///
+/// Point groups are defined as mappings with a `points` list and, optionally, the style fields of `[group.config]`.
+/// A point group (or an alias to it) can be used in `visible_points` and `joins` in the place of a group reference.
+///
+/// `include` works as in parse_config, and can include TOML and YAML files.
+///
+/// Diagnostics have the key path of the YAML document, but no position in the file.
+pub fn parse_config_yaml(file_or_string: &str, from_file: bool) -> Result<ConfigFile, ConfigError> {
+    let document = read_config_yaml(file_or_string, from_file)?;
+    let (config, mut errors) = yaml_document_table(&document)?;
+    let path = if from_file { Path::new(file_or_string) } else { Path::new("") };
+    let config = include_files(config, path, &mut errors);
+    let mut config_file = parse_config_table(config)?;
+    config_file.add_diagnostics(errors);
+    Ok(config_file)
+}
+
+/// Top level table of a YAML configuration, in the layout of the TOML format, and the problems of the translation
+pub(crate) fn yaml_config_table(content: &str) -> Result<(HashMap<String, Value>, Vec<ConfigError>), ConfigError> {
+    yaml_document_table(&read_config_yaml(content, false)?)
+}
+
+fn yaml_document_table(document: &YamlValue) -> Result<(HashMap<String, Value>, Vec<ConfigError>), ConfigError> {
+    let root = document.as_mapping().ok_or_else(|| 
+        ConfigError::new(ConfigErrorKind::Syntax, Vec::new(), "The YAML configuration must be a mapping")
+    )?;
+    let mut errors = Vec::new();
+    let config = yaml_to_config_table(root, &mut errors);
+    Ok((config, errors))
+}
+
+fn yaml_to_config_table(root: &Mapping, errors: &mut Vec<ConfigError>) -> HashMap<String, Value> {
+    let mut config: HashMap<String, Value> = HashMap::new();
+    let mut groups: Vec<(String, &YamlValue)> = Vec::new();
+
+    if let Some(YamlValue::Mapping(point_groups)) = root.get("point_groups") {
+        let mut groups_table = Map::new();
+        for (group_name, group_value) in point_groups {
+            let Some(group_name) = yaml_key(group_name, vec!["point_groups".into()], errors) else {
+                continue;
+            };
+            let (points, style) = match group_value {
+                YamlValue::Sequence(points) => (points_to_toml(points), Map::new()),
+                YamlValue::Mapping(group) => (
+                    group.get("points").and_then(|p| p.as_sequence()).map(|points| points_to_toml(points)).unwrap_or_default(),
+                    group_style_to_toml(group, &group_name, errors),
+                ),
+                _ => {
+                    errors.push(ConfigError::new(
+                        ConfigErrorKind::InvalidValue, 
+                        vec!["point_groups".into(), (&group_name).into()], 
+                        "Expected a list of points or a mapping with points",
+                    ));
+                    continue;
+                }
+            };
+            groups_table.insert(group_name.clone(), Value::Array(points));
+            if !style.is_empty() {
+                let mut group_config = Map::new();
+                group_config.insert("config".to_string(), Value::Table(style));
+                config.insert(group_name.clone(), Value::Table(group_config));
+            }
+            groups.push((group_name, group_value));
+        }
+        config.insert("point_groups".to_string(), Value::Table(groups_table));
+    }
+
+    for (key, value) in root {
+        let Some(key) = yaml_key(key, Vec::new(), errors) else {
+            continue;
+        };
+        match key.as_str() {
+            "point_groups" => {} // Already parsed
+            "aliases" | "virtual_points" | "views" | "include" => {
+                if let Some(aliases) = yaml_to_toml(value) {
+                    config.insert(key, aliases);
+                }
+            }
+            "defaults" | "global_settings" => {
+                let Some(defaults) = value.as_mapping() else {
+                    errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, vec![(&key).into()], "Expected a mapping of default values"));
+                    continue;
+                };
+                config.insert("defaults".to_string(), Value::Table(defaults_to_toml(defaults, &key, errors)));
+            }
+            _ => {
+                let Some(config_mapping) = value.as_mapping() else {
+                    errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, vec![(&key).into()], "Expected a configuration mapping"));
+                    continue;
+                };
+                let config_table = config_to_toml(&key, config_mapping, &groups, errors);
+                match config.get_mut(&key) {
+                    // Same behaviour as a TOML file that defines [key] and [key.config]
+                    Some(Value::Table(existing)) => existing.extend(config_table),
+                    _ => { config.insert(key, Value::Table(config_table)); }
+                }
+            }
+        }
+    }
+
+    config
+}
+
+fn config_to_toml(name: &str, config: &Mapping, groups: &[(String, &YamlValue)], errors: &mut Vec<ConfigError>) -> Map<String, Value> {
+    let mut table = Map::new();
+    for (key, value) in config {
+        let Some(key) = yaml_key(key, vec![name.into()], errors) else {
+            continue;
+        };
+        let converted = match (key.as_str(), value) {
+            ("visible_points", YamlValue::Sequence(points)) => Some(Value::Array(
+                points.iter().flat_map(|point| point_or_group_to_toml(point, groups)).collect()
+            )),
+            ("joins", YamlValue::Sequence(joins)) => Some(Value::Array(
+                joins.iter().enumerate().filter_map(|(i, join)| 
+                    join_to_toml(join, groups, vec![name.into(), "joins".into(), i.into()], errors)
+                ).collect()
+            )),
+            ("surfaces", YamlValue::Sequence(surfaces)) => Some(Value::Array(
+                surfaces.iter().enumerate().filter_map(|(i, surface)| 
+                    surface_to_toml(surface, groups, vec![name.into(), "surfaces".into(), i.into()], errors)
+                ).collect()
+            )),
+            ("vectors", YamlValue::Sequence(vectors)) => Some(Value::Array(
+                vectors.iter().enumerate().filter_map(|(i, vector)| 
+                    vector_to_toml(vector, vec![name.into(), "vectors".into(), i.into()], errors)
+                ).collect()
+            )),
+            ("point_color" | "join_color", color) => color_to_toml(color, vec![name.into(), (&key).into()], errors),
+            _ => yaml_to_toml(value),
+        };
+        if let Some(converted) = converted {
+            table.insert(key, converted);
+        }
+    }
+    table
+}
+
+/// A point is kept as is. A point group (a mapping with points) is replaced by a reference to the group if it is defined
+/// in point_groups, or by its points otherwise.
+fn point_or_group_to_toml(point: &YamlValue, groups: &[(String, &YamlValue)]) -> Vec<Value> {
+    match point {
+        YamlValue::Mapping(group) => {
+            match groups.iter().find(|(_, group_value)| *group_value == point) {
+                Some((group_name, _)) => vec![Value::Array(vec![Value::String(group_name.clone())])],
+                None => group.get("points").and_then(|p| p.as_sequence()).map(|points| points_to_toml(points)).unwrap_or_default(),
+            }
+        }
+        _ => yaml_to_toml(point).into_iter().collect(),
+    }
+}
+
+fn join_to_toml(join: &YamlValue, groups: &[(String, &YamlValue)], path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<Value> {
+    match join {
+        YamlValue::Sequence(points) => Some(Value::Array(
+            points.iter().flat_map(|point| point_or_group_to_toml(point, groups)).collect()
+        )),
+        YamlValue::Mapping(join_table) if join_table.contains_key("shape") => {
+            let mut table = Map::new();
+            if let Some(YamlValue::Sequence(points)) = join_table.get("points") {
+                table.insert("points".to_string(), Value::Array(
+                    points.iter().flat_map(|point| point_or_group_to_toml(point, groups)).collect()
+                ));
+            }
+            if let Some(shape) = join_table.get("shape").and_then(yaml_to_toml) {
+                table.insert("shape".to_string(), shape);
+            }
+            Some(Value::Table(table))
+        }
+        YamlValue::Mapping(_) => Some(Value::Array(point_or_group_to_toml(join, groups))),
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Expected a list of points, a point group or a mapping with points and shape"));
+            None
+        }
+    }
+}
+
+/// A surface is a list of points (as a join), or a mapping with its points and color
+fn surface_to_toml(surface: &YamlValue, groups: &[(String, &YamlValue)], path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<Value> {
+    match surface {
+        YamlValue::Mapping(surface_table) if surface_table.contains_key("color") => {
+            let mut table = Map::new();
+            if let Some(YamlValue::Sequence(points)) = surface_table.get("points") {
+                table.insert("points".to_string(), Value::Array(
+                    points.iter().flat_map(|point| point_or_group_to_toml(point, groups)).collect()
+                ));
+            }
+            if let Some(color) = surface_table.get("color").and_then(|color| color_to_toml(color, [path.clone(), vec!["color".into()]].concat(), errors)) {
+                table.insert("color".to_string(), color);
+            }
+            Some(Value::Table(table))
+        }
+        YamlValue::Sequence(_) | YamlValue::Mapping(_) => join_to_toml(surface, groups, path, errors),
+        _ => {
+            errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "Expected a list of points, a point group or a mapping with points and color"));
+            None
+        }
+    }
+}
+
+/// Vectors are written as mappings with `point`, `vector` and `scale`. They are translated to `[point, vector, scale]`.
+fn vector_to_toml(vector: &YamlValue, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<Value> {
+    match vector {
+        YamlValue::Mapping(vector_table) => {
+            let point = vector_table.get("point").and_then(yaml_to_toml);
+            let vector_name = vector_table.get("vector").and_then(yaml_to_toml);
+            let scale = vector_table.get("scale").and_then(|s| s.as_f64());
+            match (point, vector_name, scale) {
+                (Some(point), Some(vector_name), Some(scale)) => Some(Value::Array(vec![point, vector_name, Value::Float(scale)])),
+                (Some(point), Some(vector_name), None) => Some(Value::Array(vec![point, vector_name])),
+                _ => {
+                    errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, "A vector needs a point and a vector"));
+                    None
+                }
+            }
+        }
+        _ => yaml_to_toml(vector),
+    }
+}
+
+/// Style of a point group. The fields that are not points or styles (as `description`) have no place in the TOML layout,
+/// and are reported as Unsupported.
+fn group_style_to_toml(group: &Mapping, group_name: &str, errors: &mut Vec<ConfigError>) -> Map<String, Value> {
+    for key in group.keys() {
+        let Some(key) = yaml_key(key, vec!["point_groups".into(), group_name.into()], errors) else {
+            continue;
+        };
+        if key != "points" && !GROUP_STYLE_KEYS.contains(&key.as_str()) {
+            errors.push(ConfigError::new(
+                ConfigErrorKind::Unsupported,
+                vec!["point_groups".into(), group_name.into(), (&key).into()],
+                format!("Point groups have no {} field. Ignoring it", key),
+            ));
+        }
+    }
+    let mut style = Map::new();
+    for key in GROUP_STYLE_KEYS {
+        let value = match (key, group.get(key)) {
+            (_, None) => None,
+            ("point_color" | "join_color", Some(color)) => 
+                color_to_toml(color, vec!["point_groups".into(), group_name.into(), key.into()], errors),
+            (_, Some(value)) => yaml_to_toml(value),
+        };
+        if let Some(value) = value {
+            style.insert(key.to_string(), value);
+        }
+    }
+    style
+}
+
+/// Translates `defaults` (or `global_settings`, as in the first drafts of the format) to `[defaults]`.
+/// The keys can have the prefix `default_` (`default_point_color` is `point_color`).
+fn defaults_to_toml(defaults: &Mapping, key: &str, errors: &mut Vec<ConfigError>) -> Map<String, Value> {
+    let mut table = Map::new();
+    for (name, value) in defaults {
+        let Some(name) = yaml_key(name, vec![key.into()], errors) else {
+            continue;
+        };
+        let name = name.strip_prefix("default_").unwrap_or(&name).to_string();
+        let path = vec![key.into(), (&name).into()];
+        let value = match (name.as_str(), value) {
+            ("point_color" | "join_color" | "vector_color", color) => color_to_toml(color, path, errors),
+            ("vector_colors", YamlValue::Sequence(colors)) => colors.iter().enumerate()
+                .map(|(i, color)| color_to_toml(color, [path.clone(), vec![i.into()]].concat(), errors))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            (_, value) => yaml_to_toml(value),
+        };
+        if let Some(value) = value {
+            table.insert(name, value);
+        }
+    }
+    table
+}
+
+/// Colors can be written as in TOML (see ConfigColor), or as a tuple in a string (`(255, 0, 0)` or `(1.0, 0.5, 0.0)`)
+fn color_to_toml(color: &YamlValue, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<Value> {
+    match color {
+        YamlValue::String(tuple) if tuple.trim().starts_with('(') => {
+            let components = tuple
+                .trim()
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .split(',')
+                .map(|c| match c.trim().parse::<i64>() {
+                    Ok(c) => Some(Value::Integer(c)),
+                    Err(_) => c.trim().parse::<f64>().ok().map(Value::Float),
+                })
+                .collect::<Option<Vec<Value>>>();
+            match components {
+                Some(components) => Some(Value::Array(components)),
+                None => {
+                    errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Invalid color {:?}, expected (R, G, B) or (R, G, B, A)", tuple)));
+                    None
+                }
+            }
+        }
+        _ => yaml_to_toml(color),
+    }
+}
+
+fn points_to_toml(points: &[YamlValue]) -> Vec<Value> {
+    points.iter().filter_map(yaml_to_toml).collect()
+}
+
+fn yaml_key(key: &YamlValue, path: Vec<ConfigKey>, errors: &mut Vec<ConfigError>) -> Option<String> {
+    let key_string = yaml_key_string(key);
+    if key_string.is_none() {
+        errors.push(ConfigError::new(ConfigErrorKind::InvalidValue, path, format!("Invalid key {:?}", key)));
+    }
+    key_string
+}
+
+fn yaml_key_string(key: &YamlValue) -> Option<String> {
+    match key {
+        YamlValue::String(key) => Some(key.clone()),
+        YamlValue::Number(key) => Some(key.to_string()),
+        YamlValue::Bool(key) => Some(key.to_string()),
+        _ => None,
+    }
+}
+
+fn yaml_to_toml(value: &YamlValue) -> Option<Value> {
+    match value {
+        YamlValue::Null => None,
+        YamlValue::Bool(b) => Some(Value::Boolean(*b)),
+        YamlValue::Number(n) => match n.as_i64() {
+            Some(i) => Some(Value::Integer(i)),
+            None => n.as_f64().map(Value::Float),
+        },
+        YamlValue::String(s) => Some(Value::String(s.clone())),
+        YamlValue::Sequence(seq) => Some(Value::Array(seq.iter().filter_map(yaml_to_toml).collect())),
+        YamlValue::Mapping(mapping) => {
+            let mut table = Map::new();
+            for (key, value) in mapping {
+                if let (Some(key), Some(value)) = (yaml_key_string(key), yaml_to_toml(value)) {
+                    table.insert(key, value);
+                }
+            }
+            Some(Value::Table(table))
+        }
+        YamlValue::Tagged(tagged) => yaml_to_toml(&tagged.value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::c3d_config_color::ConfigColor;
+    use crate::c3d_config_toml::parse_config;
+
+    /// The configuration of assets/config_file.yaml, written in TOML
+    const CONFIG_FILE_TOML: &str = r#"
+[defaults]
+point_color = [128, 128, 128]
+point_size = 0.5
+
+[point_groups]
+head = ["LFHD", "RFHD", "RBHD", "LBHD", "LFHD"]
+shoulders = ["LSHO", "RSHO"]
+rshoulders1 = ["RSHO1", "RSHO2", "RSHO3"]
+lshoulders1 = ["LSHO1", "LSHO2", "LSHO3"]
+larm = ["LUPA1", "LUPA2", "LFRA", "LWRB"]
+rarm = ["RUPA1", "RUPA2", "RUPA3", "RFRA", "RWRB"]
+lhand = ["LWRB", "LFIN"]
+rhand = ["RWRB", "RFIN"]
+hands = ["(L|R)WRB", ".FIN"]
+objects = ["OBJ1", "OBJ2", "OBJ3", "OBJ4", "OBJ5", "OBJ6", "OBJA"]
+objects_re = ["OBJ[1-Z]"]
+lleg = ["LTHI", "LKNE", "LTIB", "LANK", "LHEE", "LTOE"]
+rleg = ["RTHI", "RKNE", "RTIB", "RANK", "RHEE", "RTOE"]
+ball = ["BALL"]
+errores = ["LWRB", "Rfemur.*"]
+
+[head.config]
+point_color = [255, 0, 0]
+point_size = 0.5
+
+[shoulders.config]
+point_color = [100, 100, 255]
+point_size = 2.0
+
+[objects.config]
+point_color = [100, 100, 100]
+line_thickness = 1.5
+join_color = [100, 100, 100]
+
+[ball.config]
+point_color = [255, 255, 255]
+point_size = 2.0
+
+[config1]
+visible_points = [["head"], ["larm"], ["rarm"], ["shoulders"], ["lleg"], ["rleg"], ["objects_re"], "BALL", "LWRB"]
+joins = [
+    [["head"]],
+    ["LUPA1", "LUPA2", "LFRA", "LWRB"],
+    [["rarm"]],
+    ["CLAV", "C7", "T10", "STRN", "CLAV"],
+    ["ThoraxS", "ThoraxI", "AbdI"],
+    ["RSHO", "LSHO"],
+    ["LASI", "RASI", "RPSI", "LPSI", "LASI"],
+    [["lleg"]],
+    [["rleg"]],
+    [["objects"]],
+]
+vectors = [["OBJ1", "LVelOBJ1", 1.5], ["OBJ2", "LVelOBJ2", 1.0], ["ThoraxC", "WThorax", 1.5]]
+
+[solo_objetos]
+visible_points = [["objects"], "BALL"]
+joins = [[["objects"]]]
+vectors = [["OBJ1", "LVelOBJ1", 1.5]]
+point_color = [255, 0, 0]
+join_color = [255, 195, 0]
+line_thickness = 2.0
+point_size = 1.5
+
+[errores]
+visible_points = [["errores"]]
+point_color = [255, 0, 0]
+join_color = [255, 195, 0]
+point_size = 10.0
+"#;
+
+    #[test]
+    fn example_config_is_the_same_as_in_toml() {
+        let yaml = parse_config_yaml(include_str!("../../../assets/config_file.yaml"), false).unwrap();
+        let toml = parse_config(CONFIG_FILE_TOML, false).unwrap();
+
+        assert_eq!(toml.get_diagnostics(), &Vec::new());
+        assert!(yaml == toml);
+        // Only the descriptions of the point groups are not kept
+        assert_eq!(yaml.get_diagnostics().len(), 14);
+        assert!(yaml.get_diagnostics().iter().all(|error| error.kind == ConfigErrorKind::Unsupported
+            && error.key_path.last() == Some(&"description".into())));
+    }
+
+    #[test]
+    fn defaults_can_have_the_default_prefix() {
+        let yaml = parse_config_yaml("defaults:\n  default_point_size: 2.0\n  line_thickness: 3.0\n  default_vector_colors: [red, \"(0, 255, 0)\", \"#0000ff\"]\n", false).unwrap();
+        let defaults = yaml.get_defaults();
+
+        assert_eq!(yaml.get_diagnostics(), &Vec::new());
+        assert_eq!(defaults.point_size, Some(2.0));
+        assert_eq!(defaults.line_thickness, Some(3.0));
+        assert_eq!(defaults.get_vector_colors(), [ConfigColor::rgb(255, 0, 0), ConfigColor::rgb(0, 255, 0), ConfigColor::rgb(0, 0, 255)]);
+    }
+
+    #[test]
+    fn colors_can_be_tuples() {
+        let yaml = parse_config_yaml("config:\n  point_color: (255, 128, 0)\n  join_color: (1.0, 0.5, 0.0)\n", false).unwrap();
+        let toml = parse_config("[config]\npoint_color = [255, 128, 0]\njoin_color = [1.0, 0.5, 0.0]\n", false).unwrap();
+
+        assert_eq!(yaml.get_diagnostics(), &Vec::new());
+        assert!(yaml == toml);
+        assert_eq!(yaml.get_point_color("LKNE", "config"), Some(ConfigColor::rgb(255, 128, 0)));
+    }
+
+    #[test]
+    fn invalid_tuple_colors_are_reported() {
+        let yaml = parse_config_yaml("config:\n  point_color: (255, red, 0)\n", false).unwrap();
+
+        assert_eq!(yaml.get_diagnostics().len(), 1);
+        assert_eq!(yaml.get_diagnostics()[0].key_path, vec!["config".into(), "point_color".into()]);
+        assert_eq!(yaml.get_point_color("LKNE", "config"), None);
+    }
+}
@@ -1,10 +1,42 @@
+mod c3d_config_axes;
+mod c3d_config_camera;
+mod c3d_config_color;
+mod c3d_config_color_map;
+mod c3d_config_defaults;
+mod c3d_config_error;
+mod c3d_config_generator;
+mod c3d_config_include;
+mod c3d_config_labels;
+mod c3d_config_metadata;
+mod c3d_config_mkr;
+mod c3d_config_selection;
 mod c3d_config_toml;
+mod c3d_config_validation;
+mod c3d_config_virtual;
+mod c3d_config_writer;
+mod c3d_config_yaml;
 
-use bevy::{asset::{io::Reader, Asset, AssetApp, AssetLoader, AssetServer, Assets, Handle, LoadContext}, prelude::{Commands, Local, Res, Resource}, reflect::TypePath};
+use bevy::{asset::{io::Reader, Asset, AssetApp, AssetEvent, AssetLoader, AssetServer, Assets, Handle, LoadContext}, prelude::{Commands, EventReader, Local, Res, ResMut, Resource}, reflect::TypePath};
 use bevy_app::{App, Plugin, Update};
 
+use crate::c3d_config_include::{config_table, include_files_with, AssetIncludes};
+use crate::c3d_config_toml::parse_config_table;
+
 pub mod prelude {
+    pub use crate::c3d_config_axes::*;
+    pub use crate::c3d_config_camera::*;
+    pub use crate::c3d_config_color::*;
+    pub use crate::c3d_config_color_map::*;
+    pub use crate::c3d_config_defaults::*;
+    pub use crate::c3d_config_error::*;
+    pub use crate::c3d_config_generator::*;
+    pub use crate::c3d_config_labels::*;
+    pub use crate::c3d_config_metadata::*;
+    pub use crate::c3d_config_mkr::*;
     pub use crate::c3d_config_toml::*;
+    pub use crate::c3d_config_validation::*;
+    pub use crate::c3d_config_virtual::*;
+    pub use crate::c3d_config_yaml::*;
 }
 
 pub use prelude::*;
@@ -16,6 +48,13 @@ pub struct ConfigState {
     pub loaded: bool,
 }
 
+/// Handle of the label table (CSV or XLSX) loaded in LabelCatalog
+#[derive(Resource, Default, Debug)]
+pub struct LabelCatalogState {
+    pub path: String,
+    pub handle: Handle<LabelCatalogAsset>,
+}
+
 /// Plugin for configuration of C3D files
 #[derive(Default)]
 pub struct C3dConfigPlugin;
@@ -27,7 +66,11 @@ impl Plugin for C3dConfigPlugin {
         app.init_resource::<ConfigState>()
             .register_asset_loader(ConfigAssetLoader)
             .init_asset::<ConfigC3dAsset>()
-            .add_systems(Update, load_config_system);
+            .init_resource::<LabelCatalogState>()
+            .init_resource::<LabelCatalog>()
+            .register_asset_loader(LabelCatalogLoader)
+            .init_asset::<LabelCatalogAsset>()
+            .add_systems(Update, (load_config_system, update_label_catalog));
     }
 }
 
@@ -40,7 +83,8 @@ pub struct ConfigC3dAsset {
     pub config: ConfigFile,
 }
 
-/// Asset loader for text files
+/// Asset loader for configuration files. The format is chosen by the extension of the file (TOML or YAML).
+/// Vicon MKR files are loaded as the starter configuration of their marker set (see MarkerSet::to_config_file).
 #[derive(Default)]
 pub struct ConfigAssetLoader;
 
@@ -48,18 +92,38 @@ pub struct ConfigAssetLoader;
 impl AssetLoader for ConfigAssetLoader {
     type Asset = ConfigC3dAsset;
     type Settings = ();
-    type Error = std::io::Error;
+    type Error = ConfigError;
 
     async fn load(
         &self,
         reader: &mut dyn Reader,
         _settings: &Self::Settings,
-        _load_context: &mut LoadContext<'_>,
+        load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
-        let _res = reader.read_to_end(&mut bytes).await;
+        reader.read_to_end(&mut bytes).await.map_err(|e| 
+            ConfigError::new(ConfigErrorKind::Io, Vec::new(), format!("Could not read {}: {}", load_context.path().display(), e))
+        )?;
         let content = String::from_utf8_lossy(&bytes).to_string();
-        let config_file = parse_config(&content, false).unwrap();
+        let extension = load_context.path().extension().and_then(|e| e.to_str()).map(str::to_string);
+        let config_file = match extension.as_deref() {
+            Some("mkr") => parse_mkr(&content, false)?.to_config_file(),
+            _ => {
+                let (config, mut errors) = config_table(&content, load_context.path())?;
+                let path = load_context.asset_path().clone();
+                let mut includes = AssetIncludes { load_context };
+                let config = include_files_with(config, path.clone(), &mut vec![path], &mut includes, &mut errors).await;
+                let mut config_file = parse_config_table(config)?;
+                config_file.add_diagnostics(errors);
+                if !matches!(extension.as_deref(), Some("yaml") | Some("yml")) {
+                    config_file.locate_diagnostics(&content);
+                }
+                config_file
+            }
+        };
+        for diagnostic in config_file.get_diagnostics() {
+            println!("Warning in {}: {}", load_context.path().display(), diagnostic);
+        }
         Ok(ConfigC3dAsset { 
             config_str: content, 
             config: config_file,
@@ -67,7 +131,64 @@ impl AssetLoader for ConfigAssetLoader {
     }
 
     fn extensions(&self) -> &[&str] {
-        &["toml"]
+        &["toml", "yaml", "yml", "mkr"]
+    }
+}
+
+/// Asset with a label table, loaded from a CSV or XLSX file
+#[derive(Asset, TypePath)]
+pub struct LabelCatalogAsset {
+    pub catalog: LabelCatalog,
+}
+
+/// Asset loader for label tables. The format is chosen by the extension of the file (CSV or XLSX).
+#[derive(Default)]
+pub struct LabelCatalogLoader;
+
+impl AssetLoader for LabelCatalogLoader {
+    type Asset = LabelCatalogAsset;
+    type Settings = ();
+    type Error = ConfigError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(|e| 
+            ConfigError::new(ConfigErrorKind::Io, Vec::new(), format!("Could not read {}: {}", load_context.path().display(), e))
+        )?;
+        let catalog = match load_context.path().extension().and_then(|e| e.to_str()) {
+            Some("xlsx") => LabelCatalog::from_xlsx(bytes),
+            _ => LabelCatalog::from_csv(&bytes),
+        }?;
+        Ok(LabelCatalogAsset { catalog })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv", "xlsx"]
+    }
+}
+
+/// Copies the label table of LabelCatalogState to the LabelCatalog resource when it is loaded or modified
+fn update_label_catalog(
+    mut events: EventReader<AssetEvent<LabelCatalogAsset>>,
+    label_assets: Res<Assets<LabelCatalogAsset>>,
+    label_state: Res<LabelCatalogState>,
+    mut catalog: ResMut<LabelCatalog>,
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } if *id == label_state.handle.id() => {
+                if let Some(asset) = label_assets.get(*id) {
+                    *catalog = asset.catalog.clone();
+                    println!("Label table {} loaded with {} labels", label_state.path, catalog.len());
+                }
+            }
+            _ => {}
+        }
     }
 }
 
@@ -0,0 +1,50 @@
+use config_plugin::ColorMap;
+
+use crate::*;
+
+/// Shows the legend of the color maps of the current configuration: the value, the palette and its range.
+pub(crate) fn draw_color_legend(
+    mut contexts: EguiContexts,
+    color_maps: Res<ColorMapValues>,
+) {
+    if color_maps.markers.is_none() && color_maps.joins.is_none() {
+        return;
+    }
+
+    egui::Window::new("Color map")
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(trajectories) = &color_maps.markers {
+                ui.label(format!("Markers: {}", trajectories.color_map.value.description()));
+                draw_gradient(ui, &trajectories.color_map, trajectories.range());
+            }
+            if let Some(trajectories) = &color_maps.joins {
+                if color_maps.markers.is_some() {
+                    ui.separator();
+                }
+                ui.label(format!("Joins: {}", trajectories.color_map.value.description()));
+                draw_gradient(ui, &trajectories.color_map, trajectories.range());
+            }
+        });
+}
+
+fn draw_gradient(ui: &mut egui::Ui, color_map: &ColorMap, range: [f64; 2]) {
+    let steps = 64;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 16.0), Sense::hover());
+    let step_width = rect.width() / steps as f32;
+    for step in 0..steps {
+        let [r, g, b] = color_map.palette.color(step as f64 / (steps - 1) as f64);
+        let left = rect.left() + step as f32 * step_width;
+        ui.painter().rect_filled(
+            egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(left + step_width + 0.5, rect.bottom())),
+            0.0,
+            egui::Color32::from_rgb(r, g, b),
+        );
+    }
+    ui.horizontal(|ui| {
+        ui.label(format!("{:.3}", range[0]));
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.label(format!("{:.3}", range[1]));
+        });
+    });
+}
@@ -0,0 +1,145 @@
+use bevy::asset::{AssetLoadFailedEvent, LoadState};
+use bevy::ecs::system::SystemParam;
+use config_plugin::ConfigError;
+
+use crate::*;
+
+/// Problems of the configuration file: the error that prevented it from loading, or the diagnostics of the values that
+/// were ignored
+type ConfigProblems = (Option<String>, Vec<ConfigError>);
+
+/// Load events and state of the configuration file, to read its problems
+#[derive(SystemParam)]
+pub(crate) struct ConfigLoad<'w, 's> {
+    asset_events: EventReader<'w, 's, AssetEvent<ConfigC3dAsset>>,
+    failed_events: EventReader<'w, 's, AssetLoadFailedEvent<ConfigC3dAsset>>,
+    asset_server: Res<'w, AssetServer>,
+    config_state: Res<'w, ConfigState>,
+    config_assets: Res<'w, Assets<ConfigC3dAsset>>,
+}
+
+impl ConfigLoad<'_, '_> {
+    /// Whether the configuration was (re)loaded, or failed to load, since the last call
+    fn loaded(&mut self) -> bool {
+        let loaded = self.asset_events.read().count() > 0;
+        let failed = self.failed_events.read().count() > 0;
+        loaded || failed
+    }
+
+    fn problems(&self) -> ConfigProblems {
+        match self.asset_server.get_load_state(&self.config_state.handle) {
+            Some(LoadState::Failed(error)) => (Some(error.to_string()), Vec::new()),
+            _ => match self.config_assets.get(&self.config_state.handle) {
+                Some(config) => (None, config.config.get_diagnostics().clone()),
+                None => (None, Vec::new()),
+            },
+        }
+    }
+}
+
+/// Shows the problems found in the configuration file: the error that prevented it from loading, or the diagnostics of
+/// the values that were ignored. The window opens again when the configuration is (re)loaded with different problems.
+pub(crate) fn draw_config_diagnostics(
+    mut contexts: EguiContexts,
+    mut open: Local<bool>,
+    mut shown: Local<ConfigProblems>,
+    mut config_load: ConfigLoad,
+    app_state: Res<AppState>,
+) {
+    if config_load.loaded() {
+        let problems = config_load.problems();
+        if problems != *shown {
+            *shown = problems;
+            *open = true;
+        }
+    }
+    let (fatal, diagnostics) = &*shown;
+    if !*open || (fatal.is_none() && diagnostics.is_empty()) {
+        return;
+    }
+
+    egui::Window::new("Configuration diagnostics")
+        .open(&mut open)
+        .scroll([false, true])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(&app_state.config_path);
+            ui.separator();
+            if let Some(fatal) = fatal {
+                ui.colored_label(egui::Color32::from_rgb(255, 80, 80), fatal);
+            }
+            for diagnostic in diagnostics {
+                ui.colored_label(egui::Color32::from_rgb(230, 180, 40), diagnostic.to_string())
+                    .on_hover_text(format!("{:?}", diagnostic.kind));
+            }
+        });
+}
+
+/// Shows the result of checking the configuration against the labels of the c3d file. The window opens again every
+/// time the c3d is loaded, if there is something to report. The issues of the current configuration are expanded.
+pub(crate) fn draw_config_validation(
+    mut contexts: EguiContexts,
+    mut open: Local<bool>,
+    app_state: Res<AppState>,
+    validation: Res<ConfigValidation>,
+) {
+    if validation.is_changed() {
+        *open = true;
+    }
+    if !*open {
+        return;
+    }
+    let Some(report) = validation.report.as_ref().filter(|report| !report.is_empty()) else {
+        return;
+    };
+    let current_config = app_state.current_config.clone().unwrap_or_default();
+
+    egui::Window::new("Configuration validation")
+        .open(&mut open)
+        .scroll([false, true])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("{} problems found with the labels of the c3d", report.issues.len()));
+            ui.separator();
+            let mut contexts = report.issues.iter().map(|issue| issue.context.as_str()).collect::<Vec<_>>();
+            contexts.dedup();
+            for context in contexts {
+                let in_current_config = context.split('.').next() == Some(current_config.as_str());
+                egui::CollapsingHeader::new(context)
+                    .default_open(in_current_config)
+                    .show(ui, |ui| {
+                        for issue in report.issues.iter().filter(|issue| issue.context == context) {
+                            ui.colored_label(egui::Color32::from_rgb(230, 180, 40), issue.to_string());
+                        }
+                    });
+            }
+        });
+}
+
+/// Tells the user which configuration was chosen automatically for the c3d, and how well it fits. The window opens again
+/// every time a configuration is chosen.
+pub(crate) fn draw_config_selection(
+    mut contexts: EguiContexts,
+    mut open: Local<bool>,
+    selection: Res<ConfigSelection>,
+) {
+    if selection.is_changed() && !selection.is_added() {
+        *open = true;
+    }
+    if !*open {
+        return;
+    }
+
+    egui::Window::new("Configuration selected")
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            match &selection.selected {
+                Some((config_name, score)) => {
+                    ui.label(format!("{} was selected for the labels of the c3d", config_name));
+                    ui.label(format!("{:.0}% of its points found", score * 100.0));
+                }
+                None => {
+                    ui.colored_label(egui::Color32::from_rgb(230, 180, 40), "No configuration fits the labels of the c3d");
+                }
+            }
+        });
+}
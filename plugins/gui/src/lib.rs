@@ -1,3 +1,5 @@
+mod color_legend;
+mod config_diagnostics;
 mod milestones;
 mod metrics_dashboard;
 mod theme;
@@ -9,15 +11,18 @@ use bevy_egui::{egui::{self, Sense}, EguiContexts, EguiPlugin};
 // #[cfg(not(target_arch = "wasm32"))]
 // use bevy_metrics_dashboard::{metrics::{describe_gauge, gauge}, DashboardPlugin, DashboardWindow, RegistryPlugin};
 
-use config_plugin::{ConfigC3dAsset, ConfigState};
+use config_plugin::{Axis, ConfigC3dAsset, ConfigFile, ConfigState, Handedness, LabAxes, LabelCatalog};
 use control_plugin::*;
 use egui_double_slider::DoubleSlider;
+use color_legend::draw_color_legend;
+use config_diagnostics::{draw_config_diagnostics, draw_config_selection, draw_config_validation};
 use milestones::{milestones_event_orchestrator, update_milestone_board, Milestones};
 use metrics_dashboard::*;
 use theme::Theme;
 use vectors::*;
 use markers::*;
 use traces::*;
+use color_maps::*;
 
 pub struct GUIPlugin;
 
@@ -29,7 +34,7 @@ impl Plugin for GUIPlugin {
                     (gui, 
                         fill_graphs, represent_graphs
                     ).chain())
-            .add_systems(Update, (milestones_event_orchestrator, graph_event_orchestrator, fill_empty_graphs, MarkersWindow::draw_floating_window, theme::set_theme))
+            .add_systems(Update, (milestones_event_orchestrator, graph_event_orchestrator, fill_empty_graphs, MarkersWindow::draw_floating_window, theme::set_theme, draw_config_diagnostics, draw_config_validation, draw_config_selection, draw_color_legend))
             .init_resource::<Graphs>()
             .init_resource::<Milestones>()
             .init_resource::<Theme>()
@@ -66,6 +71,7 @@ fn gui(
     gui_sides: ResMut<GuiSidesEnabled>,
     config_state: Res<ConfigState>,
     config_assets: Res<Assets<ConfigC3dAsset>>,
+    generated_config: Res<GeneratedConfig>,
     // markers_query: Query<(&Marker, &Transform)>,
     vectors_query: Query<(&Vector, &Visibility)>,
 ) {
@@ -103,6 +109,10 @@ fn gui(
                             .labelled_by(path_label.id)
                             .on_hover_text(path);
                     });
+                    if let Some(units) = &app_state.units {
+                        ui.label(format!("Units: {}", units))
+                            .on_hover_text("Units of the points in the c3d (POINT:UNITS). They are converted to millimetres");
+                    }
                 });
 
                 ui.vertical(|ui| {
@@ -211,14 +221,54 @@ fn gui(
                             ui.label("Select configuration");
                             let config_state = config_assets.get(&config_state.handle);
                             if let Some(config_state) = config_state {
-                                for config_name in config_state.config.get_all_config_names() {
-                                    if ui.button(config_name.clone()).clicked() {
+                                let config_file = &config_state.config;
+                                for config_name in config_file.get_all_config_names() {
+                                    let mut title = config_file.get_config_title(&config_name);
+                                    if let Some(hotkey) = config_file.get_config(&config_name).and_then(|config| config.get_metadata().hotkey) {
+                                        title = format!("{} ({})", title, hotkey);
+                                    }
+                                    let mut button = ui.button(title);
+                                    if let Some(description) = config_file.get_config_description(&config_name) {
+                                        button = button.on_hover_text(description);
+                                    }
+                                    if button.clicked() {
                                         app_state.current_config = Some(config_name.clone());
                                         app_state.change_config = true;
                                     }
                                 }
                             }
                         });
+                        let generated = generated_config.generated.as_ref().is_some_and(|(_, handle)| *handle == config_state.handle);
+                        if ui.add_enabled(generated, egui::Button::new("Save configuration"))
+                            .on_hover_text("Save the configuration generated from the labels as TOML, next to the c3d")
+                            .on_disabled_hover_text("Only the configuration generated from the labels can be saved")
+                            .clicked() {
+                            if let Some(config_asset) = config_assets.get(&config_state.handle) {
+                                save_config_next_to_c3d(&config_asset.config, &app_state.c3d_path);
+                            }
+                        }
+                        ui.menu_button("Axes", |ui| {
+                            ui.label("Up axis of the lab");
+                            let mut lab_axes = app_state.lab_axes;
+                            if ui.radio(lab_axes.is_none(), "From the configuration").clicked() {
+                                lab_axes = None;
+                            }
+                            for up in [Axis::Z, Axis::Y, Axis::X] {
+                                if ui.radio(lab_axes.is_some_and(|axes| axes.up == up), up.name()).clicked() {
+                                    let handedness = lab_axes.map(|axes| axes.handedness).unwrap_or_default();
+                                    lab_axes = Some(LabAxes { handedness, ..LabAxes::with_up(up) });
+                                }
+                            }
+                            let mut left_handed = lab_axes.is_some_and(|axes| axes.handedness == Handedness::Left);
+                            if ui.checkbox(&mut left_handed, "Left-handed").changed() {
+                                let handedness = if left_handed { Handedness::Left } else { Handedness::Right };
+                                lab_axes = Some(LabAxes { handedness, ..lab_axes.unwrap_or_default() });
+                            }
+                            if lab_axes != app_state.lab_axes {
+                                app_state.lab_axes = lab_axes;
+                                app_state.change_config = true;
+                            }
+                        });
                         if ui.button("Remove all traces").on_hover_text("Remove all traces").clicked() {
                             trace_event.send(TraceEvent::DespawnAllTracesEvent);
                         }
@@ -277,4 +327,26 @@ fn gui(
     if graphs_enabled {
         
     }
+}
+
+/// Writes the configuration to `<c3d name>.generated.toml`, so it is not taken for the configuration of the c3d. Relative
+/// paths are in the assets folder, as the c3d. An existing file is never overwritten.
+fn save_config_next_to_c3d(config: &ConfigFile, c3d_path: &str) {
+    let mut path = std::path::Path::new(c3d_path).with_extension("generated.toml");
+    if path.is_relative() {
+        path = std::path::Path::new("assets").join(path);
+    }
+    if path.exists() {
+        println!("Not saving the configuration: {} already exists", path.display());
+        return;
+    }
+    match config.save_config(&path.to_string_lossy()) {
+        Ok(not_written) => {
+            println!("Configuration saved in {}", path.display());
+            for error in not_written {
+                println!("Not saved: {}", error);
+            }
+        }
+        Err(error) => println!("Could not save the configuration in {}: {}", path.display(), error),
+    }
 }
\ No newline at end of file
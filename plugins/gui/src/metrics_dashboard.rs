@@ -107,6 +107,7 @@ impl MarkersWindow {
         query_windows: Query<(Entity, &Self)>,
         config_state: Res<ConfigState>,
         config_assets: Res<Assets<ConfigC3dAsset>>,
+        catalog: Res<LabelCatalog>,
     ) {
         let config_state = config_assets.get(&config_state.handle);
         let traces = query_traces.iter().map(|trace| trace.0.clone()).collect::<Vec<String>>();
@@ -133,7 +134,7 @@ impl MarkersWindow {
                                 let mut markers_in_config = value.get_visible_points().unwrap_or(&binding).clone();
                                 markers_in_config.sort();
                                 markers_in_config.dedup();
-                                represented_points = draw_childs(ui, &mut graphs, &mut trace_event, &markers_in_config, &traces)
+                                represented_points = draw_childs(ui, &mut graphs, &mut trace_event, &markers_in_config, &traces, &catalog)
                             });
                         });
                     }
@@ -141,7 +142,7 @@ impl MarkersWindow {
                     markers.retain(|marker| !represented_points.contains(marker));
 
                     ui.collapsing("Not in config", |ui| {
-                        draw_childs(ui, &mut graphs, &mut trace_event, &markers, &traces);
+                        draw_childs(ui, &mut graphs, &mut trace_event, &markers, &traces, &catalog);
                     });
                 });
             if !open {
@@ -157,6 +158,7 @@ fn draw_childs (
     trace_event: &mut EventWriter<TraceEvent>,
     markers: &Vec<String>,
     traces: &Vec<String>,
+    catalog: &LabelCatalog,
 ) -> Vec<String> {
     let mut represented_points = Vec::new();
     for marker in markers {
@@ -172,7 +174,7 @@ fn draw_childs (
                     false => trace_event.send(TraceEvent::AddTraceEvent(marker.clone())),
                 };
             }
-            ui.collapsing(marker.clone(), |ui| {
+            ui.collapsing(catalog.title(marker), |ui| {
                 if ui.button("Plot X").clicked() {
                     graphs.add_empty_graph(marker.to_string(), XYZ::X);
                 }
@@ -182,7 +184,7 @@ fn draw_childs (
                 if ui.button("Plot Z").clicked() {
                     graphs.add_empty_graph(marker.to_string(), XYZ::Z);
                 }
-            });
+            }).header_response.on_hover_text(catalog.tooltip(marker));
         });
         represented_points.push(marker.clone());
     }
@@ -271,6 +273,7 @@ pub(crate) fn represent_graphs(
     mut ctx: EguiContexts,
     mut commands: Commands,
     state: Res<AppState>,
+    catalog: Res<LabelCatalog>,
 ){
     let ctx  = ctx.ctx_mut();
     let mut removed_graphs = Vec::new();
@@ -298,7 +301,10 @@ pub(crate) fn represent_graphs(
                 let mut keys = graphs.graphs.keys().cloned().collect::<Vec<String>>();
                 keys.sort();
                 for (_i, marker) in keys.iter().enumerate() {
-                    ui.collapsing(marker, |ui|{
+                    // Graphs are named as `label::x`
+                    let label = marker.split("::").next().unwrap_or(marker);
+                    let title = marker.replacen(label, &catalog.title(label), 1);
+                    ui.collapsing(title, |ui|{
                         ui.horizontal(|ui| {
                             let current_y = graphs.graphs.get(marker).unwrap().secondary_plot.last().unwrap_or(&0.0);
                             
@@ -345,7 +351,7 @@ pub(crate) fn represent_graphs(
                             ui.line(principal_line);
                             ui.line(secondary_line);
                         });
-                    });
+                    }).header_response.on_hover_text(catalog.tooltip(label));
                 }
             });
         });